@@ -11,7 +11,20 @@ from pydantic_settings import BaseSettings, SettingsConfigDict
 ROOT_DIR = Path(__file__).resolve().parent.parent.parent.parent
 BACKEND_DIR = ROOT_DIR / "backend"
 FASTAPI_DIR = BACKEND_DIR / "fastapi"
-ENV_FILE = ROOT_DIR / ".env"
+
+
+def _dir_from_env(name: str) -> Optional[Path]:
+    """Directory passed in by the desktop shell, which sets these for its sidecar."""
+    value = os.environ.get(name)
+    return Path(value) if value else None
+
+
+# Run standalone, the backend keeps its files next to its sources
+DATA_DIR = _dir_from_env("SOULSENSE_DATA_DIR") or FASTAPI_DIR / "data"
+CONFIG_DIR = _dir_from_env("SOULSENSE_CONFIG_DIR") or ROOT_DIR
+CACHE_DIR = _dir_from_env("SOULSENSE_CACHE_DIR") or DATA_DIR
+EXPORT_DIR = DATA_DIR / "exports" if _dir_from_env("SOULSENSE_DATA_DIR") else Path("exports")
+ENV_FILE = CONFIG_DIR / ".env"
 
 # Only add backend-specific paths to avoid module name conflicts with main app
 if str(BACKEND_DIR) not in sys.path:
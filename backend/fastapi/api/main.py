@@ -3,6 +3,7 @@ from fastapi.responses import JSONResponse
 # Triggering reload for new community routes
 from fastapi.middleware.cors import CORSMiddleware
 from starlette.middleware.base import BaseHTTPMiddleware
+from sqlalchemy.engine import make_url
 from .config import get_settings_instance
 from .api.v1.router import api_router as api_v1_router
 from .routers.health import router as health_router
@@ -31,6 +32,12 @@ def create_app() -> FastAPI:
     from .middleware.security import SecurityHeadersMiddleware
     app.add_middleware(SecurityHeadersMiddleware)
 
+    # Desktop sidecar handshake: only the Tauri shell knows the per-launch token
+    from .middleware.sidecar_auth import SidecarTokenMiddleware, SIDECAR_TOKEN_HEADER, get_sidecar_token
+    sidecar_token = get_sidecar_token()
+    if sidecar_token:
+        app.add_middleware(SidecarTokenMiddleware, token=sidecar_token)
+
     # CORS middleware
     # If in production, ensure we are not allowing all origins blindly unless intended
     origins = settings.cors_origins
@@ -40,7 +47,7 @@ def create_app() -> FastAPI:
         allow_origins=origins,
         allow_credentials=True,
         allow_methods=["GET", "POST", "PUT", "DELETE", "OPTIONS", "PATCH"],
-        allow_headers=["Content-Type", "Authorization", "X-Requested-With", "X-API-Version"],
+        allow_headers=["Content-Type", "Authorization", "X-Requested-With", "X-API-Version", SIDECAR_TOKEN_HEADER],
         max_age=3600, # Cache preflight requests for 1 hour
     )
     
@@ -109,7 +116,9 @@ def create_app() -> FastAPI:
         print("[OK] SoulSense API started successfully")
         print(f"[ENV] Environment: {settings.app_env}")
         print(f"[CONFIG] Debug mode: {settings.debug}")
-        print(f"[DB] Database: {settings.database_url}")
+        # Never print credentials that may be part of the URL
+        db_url = make_url(settings.database_url).render_as_string(hide_password=True)
+        print(f"[DB] Database: {db_url}")
         print(f"[API] API available at /api/v1")
 
     return app
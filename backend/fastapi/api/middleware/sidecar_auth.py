@@ -0,0 +1,45 @@
+import os
+import secrets
+from typing import Optional
+
+from fastapi import Request
+from fastapi.responses import JSONResponse
+from starlette.middleware.base import BaseHTTPMiddleware
+
+# Set by the desktop shell to a random value on every launch
+SIDECAR_TOKEN_ENV = "SOULSENSE_SIDECAR_TOKEN"
+SIDECAR_TOKEN_HEADER = "X-SoulSense-Token"
+
+
+def get_sidecar_token() -> Optional[str]:
+    """Return the per-launch token if the API runs as the desktop sidecar."""
+    return os.environ.get(SIDECAR_TOKEN_ENV) or None
+
+
+class SidecarTokenMiddleware(BaseHTTPMiddleware):
+    """
+    Middleware that rejects requests lacking the desktop shell's token.
+    Keeps other local processes from reading journals and profiles
+    through the loopback port the sidecar listens on.
+    """
+    def __init__(self, app, token: str):
+        super().__init__(app)
+        self.token = token
+
+    async def dispatch(self, request: Request, call_next):
+        # Let CORS preflights through; they never carry custom headers.
+        # A bare OPTIONS request is not a preflight and still needs the token
+        if (
+            request.method == "OPTIONS"
+            and "access-control-request-method" in request.headers
+        ):
+            return await call_next(request)
+
+        presented = request.headers.get(SIDECAR_TOKEN_HEADER, "")
+        if not secrets.compare_digest(presented, self.token):
+            return JSONResponse(
+                status_code=401,
+                content={"detail": "Missing or invalid sidecar token"}
+            )
+
+        return await call_next(request)
@@ -11,8 +11,9 @@ from typing import List, Optional
 import asyncio
 import aiofiles
 
+from ..config import DATA_DIR
+
 # Path to the contact submissions JSON file
-DATA_DIR = Path(__file__).parent.parent.parent / "data"
 CONTACT_FILE = DATA_DIR / "contact_submissions.json"
 
 # Lock for atomic writes
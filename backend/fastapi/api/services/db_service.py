@@ -1,5 +1,8 @@
 """Database service for assessments and questions."""
-from sqlalchemy import create_engine, func
+import os
+import re
+
+from sqlalchemy import create_engine, event, func
 from sqlalchemy.orm import sessionmaker, Session
 from typing import List, Optional, Tuple
 from datetime import datetime
@@ -11,11 +14,37 @@ from ..config import get_settings
 
 settings = get_settings()
 
-# Create engine
-engine = create_engine(
-    settings.database_url,
-    connect_args={"check_same_thread": False} if settings.database_type == "sqlite" else {}
-)
+# Raw SQLCipher key set by the desktop shell when the database is encrypted.
+# It is kept out of DATABASE_URL so it never shows up in logs.
+DB_KEY_ENV = "SOULSENSE_DB_KEY"
+
+
+def _create_engine():
+    """Create the engine, opening SQLite through SQLCipher if a key is set."""
+    connect_args = {"check_same_thread": False} if settings.database_type == "sqlite" else {}
+    key = os.environ.get(DB_KEY_ENV)
+    if not key:
+        return create_engine(settings.database_url, connect_args=connect_args)
+
+    if not re.fullmatch(r"[0-9a-f]{64}", key):
+        raise RuntimeError(f"{DB_KEY_ENV} must be a 64 digit hex key")
+    from sqlcipher3 import dbapi2 as sqlcipher
+
+    sqlcipher_engine = create_engine(
+        settings.database_url, connect_args=connect_args, module=sqlcipher
+    )
+
+    @event.listens_for(sqlcipher_engine, "do_connect")
+    def connect_with_key(dialect, conn_rec, cargs, cparams):
+        # Key the connection before SQLAlchemy's own connect hooks read from it
+        conn = sqlcipher.connect(*cargs, **cparams)
+        conn.execute(f"PRAGMA key = \"x'{key}'\"")
+        return conn
+
+    return sqlcipher_engine
+
+
+engine = _create_engine()
 
 SessionLocal = sessionmaker(autocommit=False, autoflush=False, bind=engine)
 
@@ -7,6 +7,7 @@ from datetime import datetime, timedelta
 from typing import List, Optional, Tuple, Dict, Any
 from pathlib import Path
 from sqlalchemy.orm import Session
+from ..config import EXPORT_DIR as CONFIGURED_EXPORT_DIR
 from ..root_models import User, Score
 from app.utils.file_validation import sanitize_filename, validate_file_path
 from app.utils.atomic import atomic_write
@@ -19,14 +20,14 @@ class ExportService:
     Handles data fetching, formatting, injection prevention, and safe file writing.
     """
     
-    # Base directory for exports - relative to backend root or configured path
-    # For now, we use a directory inside the backend structure
-    EXPORT_DIR = Path("exports")
+    # Base directory for exports - the app data dir under the desktop shell,
+    # the working directory otherwise (see config.EXPORT_DIR)
+    EXPORT_DIR = CONFIGURED_EXPORT_DIR
 
     @classmethod
     def ensure_export_dir(cls):
         """Ensure export directory exists."""
-        cls.EXPORT_DIR.mkdir(exist_ok=True)
+        cls.EXPORT_DIR.mkdir(parents=True, exist_ok=True)
 
     @staticmethod
     def _sanitize_csv_field(field: Any) -> str:
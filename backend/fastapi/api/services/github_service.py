@@ -7,7 +7,7 @@ import aiofiles
 from datetime import datetime
 from typing import Dict, Any, List, Optional
 from cachetools import LRUCache
-from backend.fastapi.api.config import get_settings_instance
+from backend.fastapi.api.config import CACHE_DIR, get_settings_instance
 from app.utils.atomic import atomic_write
 
 # NLTK Setup for Sentiment Analysis
@@ -44,7 +44,7 @@ class GitHubService:
         self._client: Optional[httpx.AsyncClient] = None
         
         # Persistent Cache Setup
-        self.CACHE_FILE = os.path.join(os.path.dirname(os.path.dirname(os.path.dirname(__file__))), "data", "github_cache.json")
+        self.CACHE_FILE = str(CACHE_DIR / "github_cache.json")
         self._cache_lock = None # Lazy initialization
         self._last_save_time = 0.0
         
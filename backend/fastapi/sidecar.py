@@ -10,16 +10,80 @@ BASE_DIR = Path(__file__).resolve().parent
 sys.path.append(str(BASE_DIR))
 sys.path.append(str(BASE_DIR.parent.parent))
 
-from api.main import app
+
+def encrypt_database(source, target):
+    """Export the plaintext SQLite database at `source` into a new SQLCipher
+    database at `target`, keyed with the raw hex key in SOULSENSE_DB_KEY."""
+    import re
+
+    try:
+        import sqlcipher3
+    except ImportError:
+        sys.exit("the SQLCipher driver (sqlcipher3) is not installed")
+
+    key = os.environ.get("SOULSENSE_DB_KEY", "")
+    if not re.fullmatch(r"[0-9a-f]{64}", key):
+        sys.exit("SOULSENSE_DB_KEY must be a 64 digit hex key")
+
+    conn = sqlcipher3.connect(source)
+    try:
+        conn.execute(f"ATTACH DATABASE ? AS encrypted KEY \"x'{key}'\"", (target,))
+        conn.execute("SELECT sqlcipher_export('encrypted')")
+        conn.execute("DETACH DATABASE encrypted")
+    finally:
+        conn.close()
+
+
+def log_config():
+    """uvicorn's logging config, also writing to SOULSENSE_LOG_FILE if the
+    shell passed one."""
+    import copy
+    from uvicorn.config import LOGGING_CONFIG
+
+    config = copy.deepcopy(LOGGING_CONFIG)
+    log_file = os.environ.get("SOULSENSE_LOG_FILE")
+    if log_file:
+        config["handlers"]["file"] = {
+            "class": "logging.handlers.RotatingFileHandler",
+            "formatter": "default",
+            "filename": log_file,
+            "maxBytes": 5 * 1024 * 1024,
+            "backupCount": 2,
+            "encoding": "utf-8",
+        }
+        config["formatters"]["default"]["use_colors"] = False
+        config["loggers"]["uvicorn"]["handlers"].append("file")
+        config["loggers"]["uvicorn.access"]["handlers"].append("file")
+        config["loggers"][""] = {"handlers": ["default", "file"], "level": "INFO"}
+    return config
+
 
 def main():
     parser = argparse.ArgumentParser(description="Soul Sense API Sidecar")
     parser.add_argument("--host", default="127.0.0.1", help="Host to bind to")
     parser.add_argument("--port", type=int, default=8000, help="Port to bind to")
+    parser.add_argument("--uds", default=None, help="Unix domain socket to bind to instead of host/port")
+    parser.add_argument(
+        "--encrypt-db",
+        nargs=2,
+        metavar=("SOURCE", "TARGET"),
+        help="Encrypt the SQLite database SOURCE into TARGET with SQLCipher and exit",
+    )
     args = parser.parse_args()
 
-    print(f"Starting Soul Sense Sidecar on {args.host}:{args.port}")
-    uvicorn.run(app, host=args.host, port=args.port, log_level="info")
+    if args.encrypt_db:
+        encrypt_database(*args.encrypt_db)
+        return
+
+    # Imported here so the one-off modes above don't load the whole API
+    from api.main import app
+
+    if args.uds:
+        print(f"Starting Soul Sense Sidecar on unix:{args.uds}")
+        uvicorn.run(app, uds=args.uds, log_level="info", log_config=log_config())
+    else:
+        print(f"Starting Soul Sense Sidecar on {args.host}:{args.port}")
+        uvicorn.run(app, host=args.host, port=args.port, log_level="info", log_config=log_config())
 
 if __name__ == "__main__":
     main()
@@ -0,0 +1,55 @@
+import pytest
+from fastapi import FastAPI
+from fastapi.testclient import TestClient
+from api.middleware.sidecar_auth import SidecarTokenMiddleware, SIDECAR_TOKEN_HEADER
+
+TOKEN = "a" * 64
+
+
+@pytest.fixture
+def client():
+    app = FastAPI()
+    app.add_middleware(SidecarTokenMiddleware, token=TOKEN)
+
+    @app.api_route("/api/v1/journal", methods=["GET", "OPTIONS"])
+    def journal():
+        return {"ok": True}
+
+    return TestClient(app)
+
+
+def test_missing_token_is_rejected(client):
+    """Requests without the sidecar token never reach the API."""
+    response = client.get("/api/v1/journal")
+    assert response.status_code == 401
+
+
+def test_wrong_token_is_rejected(client):
+    """A token from another launch is rejected."""
+    response = client.get("/api/v1/journal", headers={SIDECAR_TOKEN_HEADER: "b" * 64})
+    assert response.status_code == 401
+
+
+def test_right_token_is_accepted(client):
+    """The shell's token lets the request through."""
+    response = client.get("/api/v1/journal", headers={SIDECAR_TOKEN_HEADER: TOKEN})
+    assert response.status_code == 200
+    assert response.json() == {"ok": True}
+
+
+def test_cors_preflight_bypasses_token(client):
+    """Preflights cannot carry custom headers, so they pass without the token."""
+    response = client.options(
+        "/api/v1/journal",
+        headers={
+            "Origin": "http://localhost:3000",
+            "Access-Control-Request-Method": "GET"
+        }
+    )
+    assert response.status_code != 401
+
+
+def test_bare_options_requires_token(client):
+    """An OPTIONS request that is not a preflight still needs the token."""
+    response = client.options("/api/v1/journal")
+    assert response.status_code == 401
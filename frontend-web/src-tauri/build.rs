@@ -1,3 +1,38 @@
 fn main() {
-  tauri_build::build()
+  // App commands get `allow-<command>` permissions generated, and only the
+  // windows whose capabilities grant them can call them
+  tauri_build::try_build(tauri_build::Attributes::new().app_manifest(
+    tauri_build::AppManifest::new().commands(&[
+      "api_base_url",
+      "auth_clear",
+      "auth_get_access_token",
+      "auth_store_tokens",
+      "close_to_tray",
+      "database_encrypt",
+      "database_status",
+      "do_not_disturb",
+      "export_save",
+      "lock_activity",
+      "lock_disable",
+      "lock_now",
+      "lock_set_idle_minutes",
+      "lock_set_pin",
+      "lock_status",
+      "lock_unlock",
+      "log_level",
+      "notify",
+      "readiness_retry",
+      "readiness_state",
+      "reminder_settings",
+      "set_close_to_tray",
+      "set_do_not_disturb",
+      "set_log_level",
+      "set_reminder_settings",
+      "sidecar_logs",
+      "sidecar_status",
+      "snooze_reminder",
+      "watch_export",
+    ]),
+  ))
+  .expect("failed to run tauri-build");
 }
@@ -0,0 +1,664 @@
+//! App lock behind a PIN.
+//!
+//! With a PIN set, the app starts locked and locks again after the
+//! configured idle time. Activity is reported by every page through
+//! [`activity_script`] and by window focus. While locked, the other windows
+//! are hidden behind a small lock window and come back once the PIN is
+//! entered. The PIN is only kept as an Argon2 hash in `app-lock.json` in the
+//! app config dir, together with the failed attempt count, so restarting the
+//! app does not reset the lockout. With an encrypted database the PIN also
+//! unwraps its key, see [`crate::database`].
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{
+  AppHandle, Emitter, LogicalPosition, LogicalSize, Manager, WebviewUrl, WebviewWindow,
+  WebviewWindowBuilder, WindowEvent,
+};
+
+use crate::database::{self, Database};
+use crate::paths;
+use crate::readiness::{self, SPLASH_WINDOW};
+use crate::util;
+use crate::window::MAIN_WINDOW;
+
+pub const LOCK_WINDOW: &str = "lock";
+pub const LOCKED_EVENT: &str = "lock://locked";
+pub const UNLOCKED_EVENT: &str = "lock://unlocked";
+
+const FILE_NAME: &str = "app-lock.json";
+const PIN_LEN: std::ops::RangeInclusive<usize> = 4..=12;
+const DEFAULT_IDLE_MINUTES: u32 = 5;
+const MAX_IDLE_MINUTES: u32 = 24 * 60;
+const IDLE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+/// Failed attempts allowed before each further one is delayed.
+const FREE_ATTEMPTS: u32 = 5;
+const BASE_LOCKOUT: Duration = Duration::seconds(30);
+const MAX_LOCKOUT: Duration = Duration::hours(1);
+const SALT_BYTES: usize = 16;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct LockFile {
+  /// Argon2 hash in PHC string format.
+  pin_hash: Option<String>,
+  idle_minutes: u32,
+  failed_attempts: u32,
+  locked_out_until: Option<DateTime<Utc>>,
+}
+
+impl Default for LockFile {
+  fn default() -> Self {
+    Self {
+      pin_hash: None,
+      idle_minutes: DEFAULT_IDLE_MINUTES,
+      failed_attempts: 0,
+      locked_out_until: None,
+    }
+  }
+}
+
+struct LockState {
+  file: LockFile,
+  locked: bool,
+  last_activity: DateTime<Utc>,
+  /// Windows hidden while locked, shown again on unlock.
+  hidden: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockStatus {
+  enabled: bool,
+  locked: bool,
+  idle_minutes: u32,
+  locked_out_until: Option<DateTime<Utc>>,
+}
+
+pub struct AppLock {
+  path: PathBuf,
+  state: Mutex<LockState>,
+  /// Held while a PIN is checked, so attempts are counted one at a time.
+  verifying: tokio::sync::Mutex<()>,
+}
+
+impl AppLock {
+  /// Loads the lock settings; the app starts locked if a PIN is set.
+  pub fn load(app: &AppHandle) -> tauri::Result<Self> {
+    let path = app.path().app_config_dir()?.join(FILE_NAME);
+    let file: LockFile = match fs::read_to_string(&path) {
+      Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+        log::warn!("ignoring invalid {}: {e}", path.display());
+        LockFile::default()
+      }),
+      Err(_) => LockFile::default(),
+    };
+    let locked = file.pin_hash.is_some();
+    Ok(Self {
+      path,
+      state: Mutex::new(LockState {
+        file,
+        locked,
+        last_activity: Utc::now(),
+        hidden: Vec::new(),
+      }),
+      verifying: tokio::sync::Mutex::new(()),
+    })
+  }
+
+  pub fn is_locked(&self) -> bool {
+    util::lock(&self.state).locked
+  }
+
+  /// Whether a PIN is set.
+  pub fn is_enabled(&self) -> bool {
+    util::lock(&self.state).file.pin_hash.is_some()
+  }
+
+  pub fn status(&self) -> LockStatus {
+    let state = util::lock(&self.state);
+    LockStatus {
+      enabled: state.file.pin_hash.is_some(),
+      locked: state.locked,
+      idle_minutes: state.file.idle_minutes,
+      locked_out_until: state
+        .file
+        .locked_out_until
+        .filter(|until| *until > Utc::now()),
+    }
+  }
+
+  /// Records user activity, which restarts the idle timer.
+  fn touch(&self) {
+    let mut state = util::lock(&self.state);
+    if !state.locked {
+      state.last_activity = Utc::now();
+    }
+  }
+
+  fn is_idle(&self, now: DateTime<Utc>) -> bool {
+    let state = util::lock(&self.state);
+    state.file.pin_hash.is_some()
+      && !state.locked
+      && now - state.last_activity >= Duration::minutes(state.file.idle_minutes.into())
+  }
+
+  fn save(&self, file: &LockFile) -> Result<(), String> {
+    let contents = serde_json::to_vec_pretty(file).map_err(|e| e.to_string())?;
+    paths::write_private(&self.path, &contents)
+      .map_err(|e| format!("failed to write {}: {e}", self.path.display()))
+  }
+}
+
+/// Delay imposed after `failed_attempts` wrong PINs, doubling with every
+/// attempt past the free ones.
+fn lockout(failed_attempts: u32) -> Option<Duration> {
+  let excess = failed_attempts.checked_sub(FREE_ATTEMPTS)?;
+  let lockout = BASE_LOCKOUT * 2i32.pow(excess.min(10));
+  Some(lockout.min(MAX_LOCKOUT))
+}
+
+fn validate_pin(pin: &str) -> Result<(), String> {
+  if PIN_LEN.contains(&pin.len()) && pin.bytes().all(|b| b.is_ascii_digit()) {
+    Ok(())
+  } else {
+    Err(format!(
+      "the PIN must be {} to {} digits",
+      PIN_LEN.start(),
+      PIN_LEN.end()
+    ))
+  }
+}
+
+fn hash_pin(pin: &str) -> Result<String, String> {
+  let mut salt = [0u8; SALT_BYTES];
+  getrandom::fill(&mut salt).map_err(|e| e.to_string())?;
+  let salt = SaltString::encode_b64(&salt).map_err(|e| e.to_string())?;
+  Argon2::default()
+    .hash_password(pin.as_bytes(), &salt)
+    .map(|hash| hash.to_string())
+    .map_err(|e| format!("failed to hash the PIN: {e}"))
+}
+
+fn verify_pin(pin: &str, hash: &str) -> bool {
+  PasswordHash::new(hash)
+    .map(|hash| {
+      Argon2::default()
+        .verify_password(pin.as_bytes(), &hash)
+        .is_ok()
+    })
+    .unwrap_or(false)
+}
+
+/// Switches `file` to the PIN behind `hash`. `rewrap` moves the database key
+/// to the new PIN first; if `save` then fails, `undo` gets what `rewrap`
+/// returned to move it back, and `file` keeps the old hash, so the old PIN
+/// keeps opening both.
+fn commit_pin<T>(
+  file: &mut LockFile,
+  hash: String,
+  rewrap: impl FnOnce() -> Result<T, String>,
+  save: impl FnOnce(&LockFile) -> Result<(), String>,
+  undo: impl FnOnce(T),
+) -> Result<(), String> {
+  let rewrapped = rewrap()?;
+  let previous = file.pin_hash.replace(hash);
+  if let Err(e) = save(file) {
+    file.pin_hash = previous;
+    undo(rewrapped);
+    return Err(e);
+  }
+  Ok(())
+}
+
+/// Checks `pin` against the stored hash, subject to the lockout.
+pub(crate) async fn check_pin(app: &AppHandle, pin: String) -> Result<(), String> {
+  let lock = app.state::<AppLock>();
+  let _verifying = lock.verifying.lock().await;
+  let hash = {
+    let state = util::lock(&lock.state);
+    if let Some(until) = state.file.locked_out_until {
+      let remaining = until - Utc::now();
+      if remaining > Duration::zero() {
+        return Err(format!(
+          "too many attempts, try again in {} seconds",
+          remaining.num_seconds() + 1
+        ));
+      }
+    }
+    state
+      .file
+      .pin_hash
+      .clone()
+      .ok_or_else(|| "no PIN is set".to_string())?
+  };
+
+  // Argon2 is deliberately slow, so keep it off the async workers
+  let matches = tauri::async_runtime::spawn_blocking(move || verify_pin(&pin, &hash))
+    .await
+    .map_err(|e| e.to_string())?;
+
+  let mut state = util::lock(&lock.state);
+  if matches {
+    state.file.failed_attempts = 0;
+    state.file.locked_out_until = None;
+  } else {
+    state.file.failed_attempts += 1;
+    state.file.locked_out_until =
+      lockout(state.file.failed_attempts).map(|delay| Utc::now() + delay);
+  }
+  lock.save(&state.file)?;
+
+  if matches {
+    return Ok(());
+  }
+  log::warn!(
+    "wrong PIN entered ({} failed attempts)",
+    state.file.failed_attempts
+  );
+  match lockout(state.file.failed_attempts) {
+    Some(delay) => Err(format!(
+      "wrong PIN, try again in {} seconds",
+      delay.num_seconds()
+    )),
+    None => Err("wrong PIN".to_string()),
+  }
+}
+
+/// Script run in every page that reports keyboard and pointer activity, at
+/// most every few seconds.
+pub fn activity_script() -> &'static str {
+  r#"(() => {
+  let last = 0;
+  const report = () => {
+    const now = Date.now();
+    if (now - last < 5000) return;
+    last = now;
+    window.__TAURI_INTERNALS__.invoke('lock_activity').catch(() => {});
+  };
+  for (const type of ['keydown', 'pointerdown', 'pointermove', 'wheel', 'touchstart']) {
+    window.addEventListener(type, report, { capture: true, passive: true });
+  }
+})();"#
+}
+
+/// Counts focus on `window` as activity.
+pub fn track(window: &WebviewWindow) {
+  let app = window.app_handle().clone();
+  window.on_window_event(move |event| {
+    if let WindowEvent::Focused(true) = event {
+      app.state::<AppLock>().touch();
+    }
+  });
+}
+
+/// Starts the task that locks the app once it has been idle too long.
+pub fn start(app: &AppHandle) {
+  let app = app.clone();
+  tauri::async_runtime::spawn(async move {
+    loop {
+      tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+      if app.state::<AppLock>().is_idle(Utc::now()) {
+        log::info!("locking after inactivity");
+        lock(&app);
+      }
+    }
+  });
+}
+
+pub fn is_locked(app: &AppHandle) -> bool {
+  app
+    .try_state::<AppLock>()
+    .is_some_and(|lock| lock.is_locked())
+}
+
+/// Returns whether window `label` may be shown. While locked it is shown
+/// on unlock instead, and the lock window comes to the front.
+pub fn may_show(app: &AppHandle, label: &str) -> bool {
+  let Some(lock) = app.try_state::<AppLock>() else {
+    return true;
+  };
+  {
+    let mut state = util::lock(&lock.state);
+    if !state.locked {
+      return true;
+    }
+    if !state.hidden.iter().any(|hidden| hidden == label) {
+      state.hidden.push(label.to_string());
+    }
+  }
+  present(app, None);
+  false
+}
+
+/// Hides every window behind the lock window.
+pub fn lock(app: &AppHandle) {
+  let lock = app.state::<AppLock>();
+  {
+    let mut state = util::lock(&lock.state);
+    if state.locked || state.file.pin_hash.is_none() {
+      return;
+    }
+    state.locked = true;
+  }
+
+  // Take the main window's place, then hide everything else
+  present(app, app.get_webview_window(MAIN_WINDOW).as_ref());
+  let mut hidden = Vec::new();
+  for (label, window) in app.webview_windows() {
+    if label == LOCK_WINDOW || label == SPLASH_WINDOW || !window.is_visible().unwrap_or(false) {
+      continue;
+    }
+    let _ = window.hide();
+    hidden.push(label);
+  }
+  util::lock(&lock.state).hidden.extend(hidden);
+
+  if let Err(e) = app.emit(LOCKED_EVENT, ()) {
+    log::warn!("failed to emit {LOCKED_EVENT}: {e}");
+  }
+}
+
+fn unlock(app: &AppHandle) {
+  let hidden = {
+    let lock = app.state::<AppLock>();
+    let mut state = util::lock(&lock.state);
+    state.locked = false;
+    state.last_activity = Utc::now();
+    std::mem::take(&mut state.hidden)
+  };
+  for label in &hidden {
+    if let Some(window) = app.get_webview_window(label) {
+      let _ = window.show();
+    }
+  }
+  if let Some(main) = app.get_webview_window(MAIN_WINDOW) {
+    let _ = main.set_focus();
+  }
+  if let Some(window) = app.get_webview_window(LOCK_WINDOW) {
+    let _ = window.destroy();
+  }
+  log::info!("app unlocked");
+  if let Err(e) = app.emit(UNLOCKED_EVENT, ()) {
+    log::warn!("failed to emit {UNLOCKED_EVENT}: {e}");
+  }
+}
+
+/// Brings the lock window to the front, opening it over `over` if given.
+pub fn present(app: &AppHandle, over: Option<&WebviewWindow>) {
+  if let Some(window) = app.get_webview_window(LOCK_WINDOW) {
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+    return;
+  }
+  if let Err(e) = create_lock_window(app, over) {
+    log::error!("failed to open the lock window: {e}");
+  }
+}
+
+fn create_lock_window(app: &AppHandle, over: Option<&WebviewWindow>) -> tauri::Result<()> {
+  let mut builder =
+    WebviewWindowBuilder::new(app, LOCK_WINDOW, WebviewUrl::App("lock.html".into()))
+      .title("Soul Sense is locked")
+      .resizable(false)
+      .minimizable(false)
+      .focused(true);
+  match over.filter(|window| window.is_visible().unwrap_or(false)) {
+    Some(window) => {
+      let scale = window.scale_factor()?;
+      let position: LogicalPosition<f64> = window.outer_position()?.to_logical(scale);
+      let size: LogicalSize<f64> = window.inner_size()?.to_logical(scale);
+      builder = builder
+        .position(position.x, position.y)
+        .inner_size(size.width, size.height);
+    }
+    None => builder = builder.inner_size(420.0, 320.0).center(),
+  }
+  let window = builder.build()?;
+
+  let app = app.clone();
+  window.on_window_event(move |event| {
+    if let WindowEvent::CloseRequested { api, .. } = event {
+      // While the splash is up nothing else can be reached, so quit like it does
+      if app.get_webview_window(SPLASH_WINDOW).is_some() {
+        app.exit(0);
+      } else if is_locked(&app) {
+        api.prevent_close();
+      }
+    }
+  });
+  Ok(())
+}
+
+/// Returns whether a PIN is set, whether the app is locked, and any lockout.
+#[tauri::command]
+pub fn lock_status(lock: tauri::State<'_, AppLock>) -> LockStatus {
+  lock.status()
+}
+
+/// Unlocks the app with `pin`.
+#[tauri::command]
+pub async fn lock_unlock(app: AppHandle, pin: String) -> Result<(), String> {
+  if !is_locked(&app) {
+    return Ok(());
+  }
+  check_pin(&app, pin.clone()).await?;
+  // Unlock regardless, so a broken key is reported instead of locking the user out
+  if let Err(e) = database::unlock(&app, pin).await {
+    log::error!("failed to unlock the database: {e}");
+    readiness::fail(&app, e);
+  }
+  unlock(&app);
+  Ok(())
+}
+
+/// Sets or changes the PIN; changing it requires `current_pin`.
+#[tauri::command]
+pub async fn lock_set_pin(
+  app: AppHandle,
+  current_pin: Option<String>,
+  pin: String,
+) -> Result<(), String> {
+  validate_pin(&pin)?;
+  let lock = app.state::<AppLock>();
+  let current_pin = if lock.is_enabled() {
+    let current_pin = current_pin.unwrap_or_default();
+    check_pin(&app, current_pin.clone()).await?;
+    Some(current_pin)
+  } else {
+    None
+  };
+  let new_pin = pin.clone();
+  let hash = tauri::async_runtime::spawn_blocking(move || hash_pin(&new_pin))
+    .await
+    .map_err(|e| e.to_string())??;
+  let wrap = match current_pin {
+    Some(current_pin) => database::rewrap(&app, current_pin, pin).await?,
+    None => None,
+  };
+
+  let mut state = util::lock(&lock.state);
+  commit_pin(
+    &mut state.file,
+    hash,
+    || wrap.map_or(Ok(None), |wrap| database::replace_wrap(&app, wrap)),
+    |file| lock.save(file),
+    |previous| {
+      let Some(previous) = previous else {
+        return;
+      };
+      if let Err(e) = database::replace_wrap(&app, previous) {
+        log::error!("failed to restore the database key wrap: {e}");
+      }
+    },
+  )?;
+  state.last_activity = Utc::now();
+  log::info!("app lock PIN set");
+  Ok(())
+}
+
+/// Removes the PIN, which turns the app lock off.
+#[tauri::command]
+pub async fn lock_disable(app: AppHandle, pin: String) -> Result<(), String> {
+  if app.state::<Database>().is_encrypted() {
+    return Err(
+      "the database is encrypted with the PIN, so the lock cannot be removed".to_string(),
+    );
+  }
+  check_pin(&app, pin).await?;
+  let lock = app.state::<AppLock>();
+  {
+    let mut state = util::lock(&lock.state);
+    state.file.pin_hash = None;
+    lock.save(&state.file)?;
+  }
+  if is_locked(&app) {
+    unlock(&app);
+  }
+  log::info!("app lock disabled");
+  Ok(())
+}
+
+/// Sets how many idle minutes lock the app.
+#[tauri::command]
+pub fn lock_set_idle_minutes(lock: tauri::State<'_, AppLock>, minutes: u32) -> Result<(), String> {
+  if !(1..=MAX_IDLE_MINUTES).contains(&minutes) {
+    return Err(format!("idle time must be 1 to {MAX_IDLE_MINUTES} minutes"));
+  }
+  let mut state = util::lock(&lock.state);
+  state.file.idle_minutes = minutes;
+  lock.save(&state.file)
+}
+
+/// Locks the app right away.
+#[tauri::command]
+pub async fn lock_now(app: AppHandle) -> Result<(), String> {
+  if !app.state::<AppLock>().is_enabled() {
+    return Err("no PIN is set".to_string());
+  }
+  lock(&app);
+  Ok(())
+}
+
+/// Restarts the idle timer; called by [`activity_script`].
+#[tauri::command]
+pub fn lock_activity(lock: tauri::State<'_, AppLock>) {
+  lock.touch();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lockout_starts_after_the_free_attempts() {
+    for attempts in 0..FREE_ATTEMPTS {
+      assert_eq!(lockout(attempts), None, "{attempts}");
+    }
+    assert_eq!(lockout(FREE_ATTEMPTS), Some(BASE_LOCKOUT));
+  }
+
+  #[test]
+  fn lockout_doubles_per_attempt() {
+    assert_eq!(lockout(FREE_ATTEMPTS + 1), Some(BASE_LOCKOUT * 2));
+    assert_eq!(lockout(FREE_ATTEMPTS + 2), Some(BASE_LOCKOUT * 4));
+    assert_eq!(lockout(FREE_ATTEMPTS + 3), Some(BASE_LOCKOUT * 8));
+  }
+
+  #[test]
+  fn lockout_is_capped() {
+    assert_eq!(lockout(FREE_ATTEMPTS + 7), Some(MAX_LOCKOUT));
+    assert_eq!(lockout(FREE_ATTEMPTS + 100), Some(MAX_LOCKOUT));
+    assert_eq!(lockout(u32::MAX), Some(MAX_LOCKOUT));
+  }
+
+  #[test]
+  fn accepts_pins_of_4_to_12_digits() {
+    for pin in ["0000", "1234", "98765", "123456789012"] {
+      assert_eq!(validate_pin(pin), Ok(()), "{pin}");
+    }
+  }
+
+  #[test]
+  fn rejects_malformed_pins() {
+    for pin in ["", "123", "1234567890123", "12a4", "12 34", "-1234", "١٢٣٤"] {
+      assert!(validate_pin(pin).is_err(), "{pin:?}");
+    }
+  }
+
+  #[test]
+  fn commit_pin_saves_the_new_hash_after_rewrapping() {
+    let mut file = LockFile {
+      pin_hash: Some("old".into()),
+      ..Default::default()
+    };
+    let mut saved = None;
+
+    let result = commit_pin(
+      &mut file,
+      "new".into(),
+      || Ok("new wrap"),
+      |file| {
+        saved = file.pin_hash.clone();
+        Ok(())
+      },
+      |_| panic!("nothing to undo"),
+    );
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(saved.as_deref(), Some("new"));
+    assert_eq!(file.pin_hash.as_deref(), Some("new"));
+  }
+
+  #[test]
+  fn commit_pin_restores_the_old_wrap_when_saving_fails() {
+    let mut file = LockFile {
+      pin_hash: Some("old".into()),
+      ..Default::default()
+    };
+    let mut undone = None;
+
+    let result = commit_pin(
+      &mut file,
+      "new".into(),
+      || Ok("old wrap"),
+      |_| Err("disk full".to_string()),
+      |previous| undone = Some(previous),
+    );
+
+    assert_eq!(result, Err("disk full".to_string()));
+    assert_eq!(undone, Some("old wrap"));
+    assert_eq!(file.pin_hash.as_deref(), Some("old"));
+  }
+
+  #[test]
+  fn commit_pin_keeps_the_old_pin_when_rewrapping_fails() {
+    let mut file = LockFile::default();
+
+    let result = commit_pin(
+      &mut file,
+      "new".into(),
+      || Err::<(), _>("wrong PIN".to_string()),
+      |_| panic!("must not save"),
+      |_| panic!("nothing to undo"),
+    );
+
+    assert_eq!(result, Err("wrong PIN".to_string()));
+    assert_eq!(file.pin_hash, None);
+  }
+
+  #[test]
+  fn hashed_pins_verify() {
+    let hash = hash_pin("2468").unwrap();
+    assert!(verify_pin("2468", &hash));
+    assert!(!verify_pin("2469", &hash));
+    assert!(!verify_pin("2468", "not a hash"));
+  }
+}
@@ -0,0 +1,413 @@
+//! Login session kept by the shell instead of the webview.
+//!
+//! The frontend hands the tokens from `/auth/login` to [`auth_store_tokens`]
+//! and asks [`auth_get_access_token`] for an access token before calling the
+//! API, so a script injected into the page can at most read a short-lived
+//! access token. The refresh token never goes back to the webview: the
+//! [`TokenStore`] keeps it, and only the shell sends it to `/auth/refresh`,
+//! shortly before the access token expires.
+//!
+//! A background task refreshes the access token ahead of expiry and tells
+//! every window with `auth://refreshed`, or `auth://expired` once the backend
+//! rejects the refresh token. Refreshes never overlap: the backend rotates
+//! refresh tokens, so a second attempt would present a revoked token and end
+//! the session.
+
+use std::sync::Mutex;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::header::COOKIE;
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::backend::BackendEndpoint;
+use crate::util::lock;
+use crate::token_store::TokenStore;
+
+pub const REFRESHED_EVENT: &str = "auth://refreshed";
+pub const EXPIRED_EVENT: &str = "auth://expired";
+
+const REFRESH_PATH: &str = "/api/v1/auth/refresh";
+const LOGOUT_PATH: &str = "/api/v1/auth/logout";
+/// Cookie the backend reads the refresh token from.
+const REFRESH_COOKIE: &str = "refresh_token";
+/// Access tokens expiring sooner than this are refreshed before use.
+const REFRESH_MARGIN: Duration = Duration::minutes(2);
+/// Longest the refresh task sleeps before checking again, since timers may
+/// not advance while the machine is suspended.
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+/// Wait after a refresh failed for a reason other than a rejected token.
+const RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+const MAX_TOKEN_LEN: usize = 4096;
+
+pub struct AuthState {
+  store: TokenStore,
+  access_token: Mutex<Option<AccessToken>>,
+  /// Held for the duration of a refresh.
+  refreshing: tokio::sync::Mutex<()>,
+  /// Wakes the refresh task when the access token changes.
+  changed: tokio::sync::Notify,
+}
+
+#[derive(Clone)]
+struct AccessToken {
+  token: String,
+  /// Taken from the token's `exp` claim, if it has one.
+  expires_at: Option<DateTime<Utc>>,
+}
+
+impl AccessToken {
+  fn new(token: String) -> Self {
+    let expires_at = expiry(&token);
+    Self { token, expires_at }
+  }
+
+  fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+    self
+      .expires_at
+      .map_or(true, |expires_at| expires_at - REFRESH_MARGIN > now)
+  }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Refreshed {
+  expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+  access_token: String,
+  refresh_token: Option<String>,
+}
+
+impl AuthState {
+  pub fn new(app: &AppHandle) -> tauri::Result<Self> {
+    Ok(Self {
+      store: TokenStore::new(app)?,
+      access_token: Mutex::new(None),
+      refreshing: tokio::sync::Mutex::new(()),
+      changed: tokio::sync::Notify::new(),
+    })
+  }
+
+  /// The access token, unless it is missing or about to expire.
+  fn fresh_access_token(&self) -> Option<String> {
+    let access_token = lock(&self.access_token);
+    access_token
+      .as_ref()
+      .filter(|access_token| access_token.is_fresh(Utc::now()))
+      .map(|access_token| access_token.token.clone())
+  }
+
+  /// Time until the access token is due for a refresh, or `None` without
+  /// an access token that expires.
+  fn refresh_due_in(&self) -> Option<std::time::Duration> {
+    let access_token = lock(&self.access_token);
+    let expires_at = access_token.as_ref()?.expires_at?;
+    Some(
+      (expires_at - REFRESH_MARGIN - Utc::now())
+        .to_std()
+        .unwrap_or_default(),
+    )
+  }
+
+  fn set_access_token(&self, token: String) -> Option<DateTime<Utc>> {
+    let access_token = AccessToken::new(token);
+    let expires_at = access_token.expires_at;
+    *lock(&self.access_token) = Some(access_token);
+    self.changed.notify_one();
+    expires_at
+  }
+
+  /// Forgets the access token, returning whether there was one.
+  fn take_access_token(&self) -> bool {
+    let taken = lock(&self.access_token).take().is_some();
+    self.changed.notify_one();
+    taken
+  }
+
+  fn clear(&self) -> Result<(), String> {
+    *lock(&self.access_token) = None;
+    self.changed.notify_one();
+    self.store.clear()
+  }
+}
+
+/// Reads the expiry from a JWT without verifying it; the backend does that.
+fn expiry(token: &str) -> Option<DateTime<Utc>> {
+  #[derive(Deserialize)]
+  struct Claims {
+    exp: i64,
+  }
+
+  let payload = token.split('.').nth(1)?;
+  let claims: Claims = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+  DateTime::from_timestamp(claims.exp, 0)
+}
+
+/// Tokens end up in request headers, so only visible ASCII is accepted.
+fn validate_token(token: &str, name: &str) -> Result<(), String> {
+  let valid = (1..=MAX_TOKEN_LEN).contains(&token.len())
+    && token
+      .bytes()
+      .all(|b| b.is_ascii_graphic() && !matches!(b, b';' | b',' | b'"' | b'\\'));
+  if valid {
+    Ok(())
+  } else {
+    Err(format!("invalid {name}"))
+  }
+}
+
+/// Returns a usable access token, refreshing it if needed, or `None` when
+/// the user is signed out.
+pub(crate) async fn access_token(app: &AppHandle) -> Result<Option<String>, String> {
+  match app.state::<AuthState>().fresh_access_token() {
+    Some(token) => Ok(Some(token)),
+    None => refresh(app).await,
+  }
+}
+
+/// Uses `given` if the frontend passed a token, and the session's otherwise.
+pub(crate) async fn bearer_token(app: &AppHandle, given: Option<String>) -> Result<String, String> {
+  match given {
+    Some(token) => Ok(token),
+    None => access_token(app)
+      .await?
+      .ok_or_else(|| "not signed in".to_string()),
+  }
+}
+
+/// Trades the stored refresh token for new tokens. A rejected refresh token
+/// ends the session.
+async fn refresh(app: &AppHandle) -> Result<Option<String>, String> {
+  let auth = app.state::<AuthState>();
+  let _refreshing = auth.refreshing.lock().await;
+  // Another caller may have refreshed while this one waited
+  if let Some(token) = auth.fresh_access_token() {
+    return Ok(Some(token));
+  }
+
+  let Some(refresh_token) = auth.store.load()? else {
+    // Nothing can renew the access token, so the session ends with it
+    if auth.take_access_token() {
+      log::info!("access token expired without a refresh token, signing out");
+      emit_expired(app);
+    }
+    return Ok(None);
+  };
+
+  let response = app
+    .state::<BackendEndpoint>()
+    .request(Method::POST, REFRESH_PATH)
+    .header(COOKIE, format!("{REFRESH_COOKIE}={refresh_token}"))
+    .send()
+    .await
+    .map_err(|e| format!("failed to reach the backend: {e}"))?;
+  match response.status() {
+    StatusCode::OK => {}
+    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+      log::info!("refresh token rejected, signing out");
+      auth.clear()?;
+      emit_expired(app);
+      return Ok(None);
+    }
+    status => return Err(format!("token refresh failed with {status}")),
+  }
+  let tokens: TokenResponse = response
+    .json()
+    .await
+    .map_err(|e| format!("invalid token refresh response: {e}"))?;
+  validate_token(&tokens.access_token, "access token")?;
+
+  // The backend rotates refresh tokens, so the old one is already revoked
+  if let Some(refresh_token) = &tokens.refresh_token {
+    validate_token(refresh_token, "refresh token")?;
+    auth.store.save(refresh_token)?;
+  }
+  let expires_at = auth.set_access_token(tokens.access_token.clone());
+  log::debug!("access token refreshed");
+  if let Err(e) = app.emit(REFRESHED_EVENT, Refreshed { expires_at }) {
+    log::warn!("failed to emit {REFRESHED_EVENT}: {e}");
+  }
+  Ok(Some(tokens.access_token))
+}
+
+fn emit_expired(app: &AppHandle) {
+  if let Err(e) = app.emit(EXPIRED_EVENT, ()) {
+    log::warn!("failed to emit {EXPIRED_EVENT}: {e}");
+  }
+}
+
+/// Starts the task that refreshes the access token ahead of expiry.
+pub fn start(app: &AppHandle) {
+  let app = app.clone();
+  tauri::async_runtime::spawn(async move {
+    let auth = app.state::<AuthState>();
+    loop {
+      match auth.refresh_due_in() {
+        None => {
+          auth.changed.notified().await;
+          continue;
+        }
+        Some(wait) if !wait.is_zero() => {
+          let _ = tokio::time::timeout(wait.min(CHECK_INTERVAL), auth.changed.notified()).await;
+          continue;
+        }
+        Some(_) => {}
+      }
+      if let Err(e) = refresh(&app).await {
+        log::warn!("{e}");
+        tokio::time::sleep(RETRY_INTERVAL).await;
+      }
+    }
+  });
+}
+
+/// Takes over the tokens from a login.
+#[tauri::command]
+pub async fn auth_store_tokens(
+  auth: tauri::State<'_, AuthState>,
+  access_token: String,
+  refresh_token: Option<String>,
+) -> Result<(), String> {
+  validate_token(&access_token, "access token")?;
+  let _refreshing = auth.refreshing.lock().await;
+  match &refresh_token {
+    Some(refresh_token) => {
+      validate_token(refresh_token, "refresh token")?;
+      auth.store.save(refresh_token)?;
+    }
+    // A refresh token left from an earlier session would sign back in as
+    // its user on the next refresh
+    None => auth.store.clear()?,
+  }
+  auth.set_access_token(access_token);
+  log::info!("signed in");
+  Ok(())
+}
+
+/// Returns an access token that is valid for at least a little longer, or
+/// `None` when signed out.
+#[tauri::command]
+pub async fn auth_get_access_token(app: AppHandle) -> Result<Option<String>, String> {
+  access_token(&app).await
+}
+
+/// Signs out: forgets the tokens and asks the backend to revoke the refresh
+/// token.
+#[tauri::command]
+pub async fn auth_clear(app: AppHandle) -> Result<(), String> {
+  let auth = app.state::<AuthState>();
+  let refresh_token = {
+    // Wait out a refresh in flight, which would otherwise store new tokens
+    let _refreshing = auth.refreshing.lock().await;
+    let refresh_token = auth.store.load().unwrap_or_else(|e| {
+      log::warn!("{e}");
+      None
+    });
+    auth.clear()?;
+    refresh_token
+  };
+  log::info!("signed out");
+
+  if let Some(refresh_token) = refresh_token {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+      let result = app
+        .state::<BackendEndpoint>()
+        .request(Method::POST, LOGOUT_PATH)
+        .header(COOKIE, format!("{REFRESH_COOKIE}={refresh_token}"))
+        .send()
+        .await;
+      if let Err(e) = result {
+        log::warn!("failed to revoke the refresh token: {e}");
+      }
+    });
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn jwt(claims: &str) -> String {
+    let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#);
+    let payload = URL_SAFE_NO_PAD.encode(claims);
+    format!("{header}.{payload}.signature")
+  }
+
+  #[test]
+  fn expiry_is_read_from_the_exp_claim() {
+    let token = jwt(r#"{"sub":"1","exp":1700000000}"#);
+
+    assert_eq!(expiry(&token), DateTime::from_timestamp(1_700_000_000, 0));
+  }
+
+  #[test]
+  fn malformed_tokens_have_no_expiry() {
+    assert_eq!(expiry("not-a-jwt"), None);
+    assert_eq!(expiry("header.%%%.signature"), None);
+    assert_eq!(expiry(&jwt("not json")), None);
+  }
+
+  #[test]
+  fn tokens_without_exp_have_no_expiry() {
+    assert_eq!(expiry(&jwt(r#"{"sub":"1"}"#)), None);
+    assert_eq!(expiry(&jwt(r#"{"exp":"soon"}"#)), None);
+  }
+
+  #[test]
+  fn validate_token_accepts_header_safe_tokens() {
+    assert!(validate_token(&jwt(r#"{"exp":1}"#), "access token").is_ok());
+    assert!(validate_token(&"a".repeat(MAX_TOKEN_LEN), "access token").is_ok());
+  }
+
+  #[test]
+  fn validate_token_rejects_unsafe_tokens() {
+    let too_long = "a".repeat(MAX_TOKEN_LEN + 1);
+    for token in [
+      "",
+      "a b",
+      "a;b",
+      "a,b",
+      "a\"b",
+      "a\\b",
+      "a\r\nb",
+      "jwt\u{e9}",
+      &too_long,
+    ] {
+      assert_eq!(
+        validate_token(token, "refresh token"),
+        Err("invalid refresh token".to_string()),
+        "{token:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn access_token_is_fresh_until_the_refresh_margin() {
+    let now = Utc::now();
+    let expiring_at = |expires_at| AccessToken {
+      token: String::new(),
+      expires_at,
+    };
+
+    assert!(expiring_at(Some(now + Duration::minutes(10))).is_fresh(now));
+    assert!(!expiring_at(Some(now + Duration::minutes(1))).is_fresh(now));
+    assert!(!expiring_at(Some(now - Duration::minutes(1))).is_fresh(now));
+    assert!(expiring_at(None).is_fresh(now));
+  }
+
+  #[test]
+  fn access_token_takes_its_expiry_from_the_jwt() {
+    let token = AccessToken::new(jwt(r#"{"exp":1700000000}"#));
+
+    assert_eq!(token.expires_at, DateTime::from_timestamp(1_700_000_000, 0));
+    assert_eq!(AccessToken::new("opaque".into()).expires_at, None);
+  }
+}
@@ -0,0 +1,291 @@
+//! Location of the backend API served by the sidecar.
+//!
+//! On Linux the sidecar listens on a Unix domain socket in the app runtime
+//! dir, which has no port to conflict with and is only reachable by the
+//! current user. Elsewhere, or if the socket cannot be set up, the shell
+//! reserves a free loopback port at startup instead of relying on the
+//! sidecar's default of 8000. Only the shell talks to the backend: every
+//! request carries the [`SidecarToken`], and the webview reaches the API
+//! through the [`crate::proxy`] scheme instead.
+
+#[cfg(unix)]
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::Path;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::Duration;
+
+use reqwest::Method;
+
+use crate::error::SidecarError;
+use crate::handshake::{SidecarToken, TOKEN_ENV_VAR};
+
+/// Default timeout for calls the shell makes to the backend.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest socket path that fits `sockaddr_un.sun_path` on every Unix.
+#[cfg(unix)]
+const MAX_SOCKET_PATH: usize = 100;
+
+/// How the shell reaches the sidecar.
+#[derive(Clone, Debug)]
+enum Transport {
+  Tcp { host: Ipv4Addr, port: u16 },
+  #[cfg(unix)]
+  Unix { path: PathBuf },
+}
+
+#[derive(Clone, Debug)]
+pub struct BackendEndpoint {
+  transport: Transport,
+  token: SidecarToken,
+  client: reqwest::Client,
+}
+
+impl BackendEndpoint {
+  /// Uses a Unix socket in `socket_dir` when one is given and usable,
+  /// falling back to a free loopback port.
+  pub fn new(socket_dir: Option<&Path>, token: SidecarToken) -> io::Result<Self> {
+    #[cfg(unix)]
+    if let Some(dir) = socket_dir {
+      match Self::unix(dir, token.clone()) {
+        Ok(endpoint) => return Ok(endpoint),
+        Err(e) => log::warn!("falling back to TCP for the backend: {e}"),
+      }
+    }
+    #[cfg(not(unix))]
+    let _ = socket_dir;
+
+    Self::tcp(token)
+  }
+
+  /// Picks a port that is currently free on the loopback interface.
+  ///
+  /// The probe listener is closed again before returning, so the port is
+  /// only reserved in the sense that the OS just handed it out to us.
+  fn tcp(token: SidecarToken) -> io::Result<Self> {
+    let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?.local_addr()?.port();
+    Self::tcp_on(port, token)
+  }
+
+  fn tcp_on(port: u16, token: SidecarToken) -> io::Result<Self> {
+    let host = Ipv4Addr::LOCALHOST;
+    let client = reqwest::Client::builder()
+      .no_proxy()
+      .timeout(REQUEST_TIMEOUT)
+      .build()
+      .map_err(io::Error::other)?;
+    Ok(Self {
+      transport: Transport::Tcp { host, port },
+      token,
+      client,
+    })
+  }
+
+  /// Places the socket in `dir`, which is created private to the user. The
+  /// file name includes our pid so concurrent launches never share a socket.
+  #[cfg(unix)]
+  fn unix(dir: &Path, token: SidecarToken) -> io::Result<Self> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let path = dir.join(format!("backend-{}.sock", std::process::id()));
+    if path.as_os_str().len() > MAX_SOCKET_PATH {
+      return Err(io::Error::other(format!(
+        "socket path {} is too long",
+        path.display()
+      )));
+    }
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+
+    let client = reqwest::Client::builder()
+      .unix_socket(path.as_path())
+      .timeout(REQUEST_TIMEOUT)
+      .build()
+      .map_err(io::Error::other)?;
+    Ok(Self {
+      transport: Transport::Unix { path },
+      token,
+      client,
+    })
+  }
+
+  /// Prepares the transport right before the sidecar binds it: the port must
+  /// still be free, and a socket left over from a killed run is removed.
+  pub fn ensure_available(&self) -> Result<(), SidecarError> {
+    match &self.transport {
+      Transport::Tcp { host, port } => TcpListener::bind((*host, *port))
+        .map(drop)
+        .map_err(|_| SidecarError::PortConflict { port: *port }),
+      #[cfg(unix)]
+      Transport::Unix { .. } => {
+        self.release();
+        Ok(())
+      }
+    }
+  }
+
+  /// Removes the socket file, if any, once the sidecar is gone.
+  pub fn release(&self) {
+    #[cfg(unix)]
+    if let Transport::Unix { path } = &self.transport {
+      if let Err(e) = fs::remove_file(path) {
+        if e.kind() != io::ErrorKind::NotFound {
+          log::warn!("failed to remove {}: {e}", path.display());
+        }
+      }
+    }
+  }
+
+  /// The TCP port, when the backend is reached over TCP.
+  pub fn port(&self) -> Option<u16> {
+    match self.transport {
+      Transport::Tcp { port, .. } => Some(port),
+      #[cfg(unix)]
+      Transport::Unix { .. } => None,
+    }
+  }
+
+  /// Origin of the backend, e.g. `http://127.0.0.1:51234`. Over a Unix
+  /// socket the host only ends up in the `Host` header.
+  pub fn origin(&self) -> String {
+    match &self.transport {
+      Transport::Tcp { host, port } => format!("http://{host}:{port}"),
+      #[cfg(unix)]
+      Transport::Unix { .. } => "http://localhost".into(),
+    }
+  }
+
+  /// Starts an authenticated request to `path` (which may include a query),
+  /// relative to the backend origin.
+  pub fn request(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+    let request = self
+      .client
+      .request(method, format!("{}{path}", self.origin()));
+    self.token.apply(request)
+  }
+
+  /// Starts an authenticated `GET` request to `path`.
+  pub fn get(&self, path: &str) -> reqwest::RequestBuilder {
+    self.request(Method::GET, path)
+  }
+
+  /// Command-line arguments for `sidecar.py`.
+  pub fn sidecar_args(&self) -> Vec<String> {
+    match &self.transport {
+      Transport::Tcp { host, port } => vec![
+        "--host".into(),
+        host.to_string(),
+        "--port".into(),
+        port.to_string(),
+      ],
+      #[cfg(unix)]
+      Transport::Unix { path } => vec!["--uds".into(), path.display().to_string()],
+    }
+  }
+
+  /// Environment variables for the sidecar process.
+  ///
+  /// CORS is switched off entirely: browsers never talk to the sidecar
+  /// directly, only the shell does.
+  pub fn sidecar_env(&self) -> [(&'static str, &str); 2] {
+    [
+      (TOKEN_ENV_VAR, self.token.as_str()),
+      ("ALLOWED_ORIGINS", "[]"),
+    ]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::{BufRead, BufReader, Write};
+  use std::thread;
+
+  use super::*;
+  use crate::handshake::TOKEN_HEADER;
+
+  /// Serves HTTP on a loopback port, answering `200` only to requests whose
+  /// token header is exactly `expected`, and returns the port.
+  fn spawn_stand_in(expected: String) -> u16 {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    thread::spawn(move || {
+      for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut presented = None;
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+          if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case(TOKEN_HEADER) {
+              presented = Some(value.trim().to_string());
+            }
+          }
+          line.clear();
+        }
+
+        let status = match presented {
+          Some(presented) if presented == expected => "200 OK",
+          _ => "401 Unauthorized",
+        };
+        let _ = write!(
+          stream,
+          "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        );
+      }
+    });
+
+    port
+  }
+
+  #[tokio::test]
+  async fn request_without_token_is_rejected() {
+    let port = spawn_stand_in(SidecarToken::generate().unwrap().as_str().into());
+
+    let response = reqwest::Client::new()
+      .get(format!("http://127.0.0.1:{port}/api/v1/journal"))
+      .send()
+      .await
+      .unwrap();
+
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+  }
+
+  #[tokio::test]
+  async fn request_with_token_from_another_launch_is_rejected() {
+    let port = spawn_stand_in(SidecarToken::generate().unwrap().as_str().into());
+    let endpoint = BackendEndpoint::tcp_on(port, SidecarToken::generate().unwrap()).unwrap();
+
+    let response = endpoint.get("/api/v1/journal").send().await.unwrap();
+
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+  }
+
+  #[tokio::test]
+  async fn request_carries_the_token() {
+    let token = SidecarToken::generate().unwrap();
+    let port = spawn_stand_in(token.as_str().into());
+    let endpoint = BackendEndpoint::tcp_on(port, token).unwrap();
+
+    let response = endpoint
+      .request(Method::POST, "/api/v1/journal")
+      .send()
+      .await
+      .unwrap();
+
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+  }
+
+  #[test]
+  fn tokens_are_unique_per_launch() {
+    let first = SidecarToken::generate().unwrap();
+    let second = SidecarToken::generate().unwrap();
+
+    assert_eq!(first.as_str().len(), 64);
+    assert_ne!(first.as_str(), second.as_str());
+  }
+}
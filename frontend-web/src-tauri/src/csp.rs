@@ -0,0 +1,132 @@
+//! Content Security Policy for the app's webviews.
+//!
+//! The policy only lets pages load code from the bundled frontend and talk
+//! to the app's own origin (Next.js fetches its RSC payloads from there), the
+//! IPC bridge and the `soulsense-api` proxy, the sidecar's only origin as far
+//! as the webview is concerned. Inline scripts are only allowed by the
+//! hashes Tauri adds for the ones Next.js emits. Inline styles are allowed
+//! because the frontend sets style attributes at runtime, so Tauri is told
+//! not to add style hashes, which would switch `'unsafe-inline'` off.
+//!
+//! The policy is applied to the context at startup. `tauri.conf.json` keeps
+//! a copy because Tauri only hashes inline scripts when a CSP is configured
+//! at build time; a test keeps the two in sync.
+
+use tauri::utils::config::Csp;
+use tauri::{Context, Url};
+
+/// Origins of the proxy scheme: `http://<scheme>.localhost` on Windows and
+/// Android, `<scheme>://localhost` elsewhere.
+const PROXY_ORIGINS: &[&str] = &[
+  "soulsense-api://localhost",
+  "http://soulsense-api.localhost",
+];
+
+/// Origins of the IPC bridge, in the same two forms.
+const IPC_ORIGINS: &[&str] = &["ipc:", "http://ipc.localhost"];
+
+/// Builds the policy; `dev_url` loosens it for the frontend dev server.
+pub fn policy(dev_url: Option<&Url>) -> String {
+  let mut script_src = sources(&["'self'"]);
+  let mut connect_src = sources(&["'self'"]);
+  connect_src.extend(sources(IPC_ORIGINS));
+  connect_src.extend(sources(PROXY_ORIGINS));
+  if let Some(dev_url) = dev_url {
+    // Hot reloading evaluates code and talks to the dev server over a socket
+    script_src.push("'unsafe-eval'".to_string());
+    let origin = dev_url.origin().ascii_serialization();
+    connect_src.push(origin.replacen("http", "ws", 1));
+    connect_src.push(origin);
+  }
+
+  let directives = [
+    ("default-src", sources(&["'self'"])),
+    ("script-src", script_src),
+    ("style-src", sources(&["'self'", "'unsafe-inline'"])),
+    ("img-src", sources(&["'self'", "data:", "blob:"])),
+    ("font-src", sources(&["'self'", "data:"])),
+    ("connect-src", connect_src),
+    ("object-src", sources(&["'none'"])),
+    ("frame-src", sources(&["'none'"])),
+    ("frame-ancestors", sources(&["'none'"])),
+    ("base-uri", sources(&["'self'"])),
+    ("form-action", sources(&["'self'"])),
+  ];
+  directives
+    .iter()
+    .map(|(name, sources)| format!("{name} {}", sources.join(" ")))
+    .collect::<Vec<_>>()
+    .join("; ")
+}
+
+fn sources(list: &[&str]) -> Vec<String> {
+  list.iter().map(|source| source.to_string()).collect()
+}
+
+/// Installs the generated policy into the app context.
+pub fn apply(context: &mut Context) {
+  let dev_url = context.config().build.dev_url.clone();
+  let security = &mut context.config_mut().app.security;
+  security.csp = Some(Csp::Policy(policy(None)));
+  security.dev_csp = Some(Csp::Policy(policy(dev_url.as_ref())));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn directive<'a>(policy: &'a str, name: &str) -> Vec<&'a str> {
+    policy
+      .split("; ")
+      .find_map(|directive| directive.strip_prefix(name)?.strip_prefix(' '))
+      .unwrap_or_else(|| panic!("missing {name}"))
+      .split(' ')
+      .collect()
+  }
+
+  #[test]
+  fn release_policy_is_strict() {
+    let policy = policy(None);
+
+    assert_eq!(directive(&policy, "default-src"), ["'self'"]);
+    assert_eq!(directive(&policy, "script-src"), ["'self'"]);
+    assert_eq!(directive(&policy, "object-src"), ["'none'"]);
+    assert_eq!(directive(&policy, "frame-ancestors"), ["'none'"]);
+    assert!(!policy.contains("unsafe-eval"));
+    assert!(!policy.contains('*'));
+    assert!(!policy.contains("localhost:"));
+    assert!(!policy.contains("127.0.0.1"));
+
+    let connect_src = directive(&policy, "connect-src");
+    assert_eq!(connect_src[0], "'self'");
+    for origin in PROXY_ORIGINS.iter().chain(IPC_ORIGINS) {
+      assert!(connect_src.contains(origin), "connect-src lacks {origin}");
+    }
+    assert_eq!(connect_src.len(), 1 + PROXY_ORIGINS.len() + IPC_ORIGINS.len());
+  }
+
+  #[test]
+  fn dev_policy_allows_the_dev_server() {
+    let dev_url = Url::parse("http://localhost:3005").unwrap();
+    let policy = policy(Some(&dev_url));
+
+    assert!(directive(&policy, "script-src").contains(&"'unsafe-eval'"));
+    let connect_src = directive(&policy, "connect-src");
+    assert!(connect_src.contains(&"'self'"));
+    assert!(connect_src.contains(&"http://localhost:3005"));
+    assert!(connect_src.contains(&"ws://localhost:3005"));
+  }
+
+  #[test]
+  fn config_matches_the_release_policy() {
+    let config: serde_json::Value =
+      serde_json::from_str(include_str!("../tauri.conf.json")).unwrap();
+    let security = &config["app"]["security"];
+
+    assert_eq!(security["csp"].as_str(), Some(policy(None).as_str()));
+    assert_eq!(
+      security["dangerousDisableAssetCspModification"],
+      serde_json::json!(["style-src"])
+    );
+  }
+}
@@ -0,0 +1,494 @@
+//! Location and encryption of the backend database.
+//!
+//! The shell owns the SQLite file, `soulsense.db` in the app data dir, and
+//! hands the backend an explicit `DATABASE_URL` instead of letting it resolve
+//! a path relative to its working directory. A database left at the old
+//! relative location is moved over on first launch.
+//!
+//! In encrypted mode the backend opens the file through SQLCipher with a
+//! random key, passed in `SOULSENSE_DB_KEY` rather than in the URL, which the
+//! backend prints. The key is only stored wrapped under a key derived from the
+//! app lock PIN with Argon2, so the backend cannot start until the app is
+//! unlocked, and changing the PIN only rewraps the key. Turning encryption on
+//! exports the plaintext database into an encrypted copy, using the backend
+//! binary in a one-off mode, right before the backend is next spawned.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_shell::ShellExt;
+
+use crate::app_lock::{self, AppLock};
+use crate::paths::{self, AppDirs};
+use crate::sidecar::{self, SIDECAR_NAME};
+use crate::util;
+
+const CONFIG_FILE: &str = "database.json";
+/// Where the backend kept its database by default, relative to its working
+/// directory.
+const LEGACY_PATH: &str = "../../data/soulsense.db";
+/// SQLite files that belong to the database next to the main file.
+const SIDE_FILES: &[&str] = &["-journal", "-wal", "-shm"];
+/// Environment variable the backend reads the raw key from.
+const KEY_ENV_VAR: &str = "SOULSENSE_DB_KEY";
+const KEY_BYTES: usize = 32;
+const SALT_BYTES: usize = 16;
+const NONCE_BYTES: usize = 12;
+/// Binds the wrapped key to its purpose.
+const CONTEXT: &[u8] = b"soulsense database key v1";
+
+type DatabaseKey = [u8; KEY_BYTES];
+
+/// The database key, wrapped under a key derived from the PIN.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Encryption {
+  salt: String,
+  wrapped_key: String,
+}
+
+impl Encryption {
+  fn seal(key: &DatabaseKey, pin: &str) -> Result<Self, String> {
+    let mut salt = [0u8; SALT_BYTES];
+    let mut nonce = [0u8; NONCE_BYTES];
+    getrandom::fill(&mut salt).map_err(|e| e.to_string())?;
+    getrandom::fill(&mut nonce).map_err(|e| e.to_string())?;
+    let payload = Payload {
+      msg: key.as_slice(),
+      aad: CONTEXT,
+    };
+    let ciphertext = ChaCha20Poly1305::new(&derive_kek(pin, &salt)?)
+      .encrypt(Nonce::from_slice(&nonce), payload)
+      .map_err(|_| "failed to wrap the database key".to_string())?;
+
+    let mut wrapped_key = nonce.to_vec();
+    wrapped_key.extend_from_slice(&ciphertext);
+    Ok(Self {
+      salt: STANDARD.encode(salt),
+      wrapped_key: STANDARD.encode(wrapped_key),
+    })
+  }
+
+  fn open(&self, pin: &str) -> Result<DatabaseKey, String> {
+    let invalid = || "the stored database key is invalid".to_string();
+    let salt = STANDARD.decode(&self.salt).map_err(|_| invalid())?;
+    let wrapped_key = STANDARD.decode(&self.wrapped_key).map_err(|_| invalid())?;
+    if wrapped_key.len() < NONCE_BYTES {
+      return Err(invalid());
+    }
+    let (nonce, ciphertext) = wrapped_key.split_at(NONCE_BYTES);
+    let payload = Payload {
+      msg: ciphertext,
+      aad: CONTEXT,
+    };
+    let key = ChaCha20Poly1305::new(&derive_kek(pin, &salt)?)
+      .decrypt(Nonce::from_slice(nonce), payload)
+      .map_err(|_| "the PIN does not unlock the database".to_string())?;
+    key.try_into().map_err(|_| invalid())
+  }
+}
+
+fn derive_kek(pin: &str, salt: &[u8]) -> Result<Key, String> {
+  let mut kek = [0u8; KEY_BYTES];
+  Argon2::default()
+    .hash_password_into(pin.as_bytes(), salt, &mut kek)
+    .map_err(|e| format!("failed to derive the database key: {e}"))?;
+  Ok(Key::from(kek))
+}
+
+fn hex(key: &DatabaseKey) -> String {
+  key.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// SQLAlchemy URL of the database at `path`. It never carries the key.
+pub(crate) fn database_url(path: &Path) -> String {
+  format!("sqlite:///{}", path.display())
+}
+
+/// Environment variable handing the backend `key`, which makes it open the
+/// database through SQLCipher.
+fn key_env(key: Option<&DatabaseKey>) -> Option<(&'static str, String)> {
+  key.map(|key| (KEY_ENV_VAR, hex(key)))
+}
+
+/// Moves `from` to `to`, copying if they are on different file systems.
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+  if fs::rename(from, to).is_ok() {
+    return Ok(());
+  }
+  fs::copy(from, to)?;
+  fs::remove_file(from)
+}
+
+/// `path` with one of the [`SIDE_FILES`] suffixes.
+fn side_file(path: &Path, suffix: &str) -> PathBuf {
+  let mut side_file = path.as_os_str().to_owned();
+  side_file.push(suffix);
+  side_file.into()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct DatabaseFile {
+  encryption: Option<Encryption>,
+}
+
+/// Encryption requested but not yet applied to the database file.
+struct PendingEncryption {
+  encryption: Encryption,
+  key: DatabaseKey,
+}
+
+struct DatabaseState {
+  file: DatabaseFile,
+  /// The unwrapped key, once the PIN was entered.
+  key: Option<DatabaseKey>,
+  pending: Option<PendingEncryption>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseStatus {
+  path: PathBuf,
+  encrypted: bool,
+}
+
+pub struct Database {
+  path: PathBuf,
+  config_path: PathBuf,
+  state: Mutex<DatabaseState>,
+}
+
+impl Database {
+  pub fn load(dirs: &AppDirs) -> Self {
+    let config_path = dirs.config.join(CONFIG_FILE);
+    let file = match fs::read_to_string(&config_path) {
+      Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+        log::warn!("ignoring invalid {}: {e}", config_path.display());
+        DatabaseFile::default()
+      }),
+      Err(_) => DatabaseFile::default(),
+    };
+    Self {
+      path: dirs.database_path(),
+      config_path,
+      state: Mutex::new(DatabaseState {
+        file,
+        key: None,
+        pending: None,
+      }),
+    }
+  }
+
+  pub fn is_encrypted(&self) -> bool {
+    let state = util::lock(&self.state);
+    state.file.encryption.is_some() || state.pending.is_some()
+  }
+
+  /// Whether the backend has to wait for the PIN before it can start.
+  pub fn needs_key(&self) -> bool {
+    let state = util::lock(&self.state);
+    state.file.encryption.is_some() && state.key.is_none()
+  }
+
+  /// URL the backend opens the database with.
+  pub fn url(&self) -> String {
+    database_url(&self.path)
+  }
+
+  /// Environment variables for the sidecar process: the key, once unlocked.
+  pub fn sidecar_env(&self) -> Option<(&'static str, String)> {
+    key_env(util::lock(&self.state).key.as_ref())
+  }
+
+  fn status(&self) -> DatabaseStatus {
+    DatabaseStatus {
+      path: self.path.clone(),
+      encrypted: self.is_encrypted(),
+    }
+  }
+
+  fn save(&self, file: &DatabaseFile) -> Result<(), String> {
+    let contents = serde_json::to_vec_pretty(file).map_err(|e| e.to_string())?;
+    paths::write_private(&self.config_path, &contents)
+      .map_err(|e| format!("failed to write {}: {e}", self.config_path.display()))
+  }
+
+  /// Moves a database from the old relative location, if there is no
+  /// database yet, so no plaintext copy is left behind.
+  fn import_legacy(&self) -> Result<(), String> {
+    if self.path.exists() || self.is_encrypted() {
+      return Ok(());
+    }
+    let Some(legacy) = std::env::current_dir()
+      .ok()
+      .map(|dir| dir.join(LEGACY_PATH))
+      .filter(|legacy| legacy.is_file())
+    else {
+      return Ok(());
+    };
+    // The main file goes last: its presence marks the import as done
+    for suffix in SIDE_FILES {
+      let from = side_file(&legacy, suffix);
+      if from.exists() {
+        move_file(&from, &side_file(&self.path, suffix))
+          .map_err(|e| format!("failed to import {}: {e}", from.display()))?;
+      }
+    }
+    move_file(&legacy, &self.path)
+      .map_err(|e| format!("failed to import {}: {e}", legacy.display()))?;
+    log::info!("imported the database from {}", legacy.display());
+    Ok(())
+  }
+
+  fn encrypting_path(&self) -> PathBuf {
+    self.path.with_extension("db.encrypting")
+  }
+
+  /// Puts the encrypted copy in place of the plaintext database.
+  fn replace_with_encrypted(&self) -> Result<(), String> {
+    let encrypting = self.encrypting_path();
+    if !encrypting.exists() {
+      return Ok(());
+    }
+    for suffix in SIDE_FILES {
+      let _ = fs::remove_file(side_file(&self.path, suffix));
+    }
+    fs::rename(&encrypting, &self.path)
+      .map_err(|e| format!("failed to replace {}: {e}", self.path.display()))
+  }
+}
+
+/// Gets the database ready for the next backend spawn: imports a legacy
+/// database and applies pending encryption.
+pub(crate) async fn prepare(app: &AppHandle) -> Result<(), String> {
+  let database = app.state::<Database>();
+  if database.needs_key() {
+    return Err("the database is locked".to_string());
+  }
+  database.import_legacy()?;
+
+  // Finish an encryption interrupted after the key was saved
+  if util::lock(&database.state).file.encryption.is_some() {
+    database.replace_with_encrypted()?;
+  }
+
+  let pending = util::lock(&database.state).pending.take();
+  if let Some(pending) = pending {
+    if let Err(e) = encrypt(app, &database, &pending).await {
+      let _ = fs::remove_file(database.encrypting_path());
+      return Err(e);
+    }
+  }
+  Ok(())
+}
+
+/// Exports the plaintext database into an encrypted copy, saves the wrapped
+/// key and swaps the copy in.
+async fn encrypt(
+  app: &AppHandle,
+  database: &Database,
+  pending: &PendingEncryption,
+) -> Result<(), String> {
+  if database.path.exists() {
+    let encrypting = database.encrypting_path();
+    let _ = fs::remove_file(&encrypting);
+    log::info!("encrypting the database");
+    let output = app
+      .shell()
+      .sidecar(SIDECAR_NAME)
+      .map_err(|e| e.to_string())?
+      .args([
+        "--encrypt-db".as_ref(),
+        database.path.as_os_str(),
+        encrypting.as_os_str(),
+      ])
+      .env(KEY_ENV_VAR, hex(&pending.key))
+      .output()
+      .await
+      .map_err(|e| format!("failed to run the database export: {e}"))?;
+    if !output.status.success() {
+      return Err(format!(
+        "the database export failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+      ));
+    }
+  }
+
+  let mut state = util::lock(&database.state);
+  state.file.encryption = Some(pending.encryption.clone());
+  database.save(&state.file)?;
+  state.key = Some(pending.key);
+  database.replace_with_encrypted()?;
+  log::info!("database encryption enabled");
+  Ok(())
+}
+
+/// Unwraps the database key with `pin` and starts the backend if it was
+/// waiting for it.
+pub(crate) async fn unlock(app: &AppHandle, pin: String) -> Result<(), String> {
+  let database = app.state::<Database>();
+  let encryption = {
+    let state = util::lock(&database.state);
+    match state.key {
+      Some(_) => None,
+      None => state.file.encryption.clone(),
+    }
+  };
+  let Some(encryption) = encryption else {
+    return Ok(());
+  };
+
+  let key = tauri::async_runtime::spawn_blocking(move || encryption.open(&pin))
+    .await
+    .map_err(|e| e.to_string())??;
+  util::lock(&database.state).key = Some(key);
+  sidecar::start(app);
+  Ok(())
+}
+
+/// Wraps the database key under `pin` instead of `current_pin`, without
+/// storing the result; see [`replace_wrap`]. `None` if the database is not
+/// encrypted.
+pub(crate) async fn rewrap(
+  app: &AppHandle,
+  current_pin: String,
+  pin: String,
+) -> Result<Option<Encryption>, String> {
+  let database = app.state::<Database>();
+  let encryption = {
+    let state = util::lock(&database.state);
+    if state.pending.is_some() {
+      return Err("the database is still being encrypted".to_string());
+    }
+    state.file.encryption.clone()
+  };
+  let Some(encryption) = encryption else {
+    return Ok(None);
+  };
+
+  let encryption = tauri::async_runtime::spawn_blocking(move || {
+    let key = encryption.open(&current_pin)?;
+    Encryption::seal(&key, &pin)
+  })
+  .await
+  .map_err(|e| e.to_string())??;
+  Ok(Some(encryption))
+}
+
+/// Stores `encryption` as the wrap of the database key and returns the one
+/// it replaces, so a failed PIN change can put it back.
+pub(crate) fn replace_wrap(
+  app: &AppHandle,
+  encryption: Encryption,
+) -> Result<Option<Encryption>, String> {
+  let database = app.state::<Database>();
+  let mut state = util::lock(&database.state);
+  let previous = state.file.encryption.replace(encryption);
+  if let Err(e) = database.save(&state.file) {
+    state.file.encryption = previous;
+    return Err(e);
+  }
+  Ok(previous)
+}
+
+/// Returns where the database lives and whether it is encrypted.
+#[tauri::command]
+pub fn database_status(database: tauri::State<'_, Database>) -> DatabaseStatus {
+  database.status()
+}
+
+/// Encrypts the database with a key protected by the app lock PIN. The
+/// backend restarts to apply it.
+#[tauri::command]
+pub async fn database_encrypt(app: AppHandle, pin: String) -> Result<(), String> {
+  if !app.state::<AppLock>().is_enabled() {
+    return Err("set an app lock PIN first".to_string());
+  }
+  app_lock::check_pin(&app, pin.clone()).await?;
+  let database = app.state::<Database>();
+  if database.is_encrypted() {
+    return Ok(());
+  }
+
+  let pending = tauri::async_runtime::spawn_blocking(move || {
+    let mut key = [0u8; KEY_BYTES];
+    getrandom::fill(&mut key).map_err(|e| e.to_string())?;
+    let encryption = Encryption::seal(&key, &pin)?;
+    Ok::<_, String>(PendingEncryption { encryption, key })
+  })
+  .await
+  .map_err(|e| e.to_string())??;
+  util::lock(&database.state).pending = Some(pending);
+  sidecar::restart(&app);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const KEY: DatabaseKey = [7; KEY_BYTES];
+
+  #[test]
+  fn sealed_key_opens_with_the_pin() {
+    let encryption = Encryption::seal(&KEY, "1234").unwrap();
+    assert_eq!(encryption.open("1234"), Ok(KEY));
+  }
+
+  #[test]
+  fn sealing_is_salted() {
+    let first = Encryption::seal(&KEY, "1234").unwrap();
+    let second = Encryption::seal(&KEY, "1234").unwrap();
+    assert_ne!(first.salt, second.salt);
+    assert_ne!(first.wrapped_key, second.wrapped_key);
+  }
+
+  #[test]
+  fn wrong_pin_does_not_open_the_key() {
+    let encryption = Encryption::seal(&KEY, "1234").unwrap();
+    assert_eq!(
+      encryption.open("1235"),
+      Err("the PIN does not unlock the database".to_string())
+    );
+  }
+
+  #[test]
+  fn truncated_wrapped_key_is_rejected() {
+    let mut encryption = Encryption::seal(&KEY, "1234").unwrap();
+    let wrapped_key = STANDARD.decode(&encryption.wrapped_key).unwrap();
+
+    for len in [0, NONCE_BYTES - 1, NONCE_BYTES, wrapped_key.len() - 1] {
+      encryption.wrapped_key = STANDARD.encode(&wrapped_key[..len]);
+      assert!(encryption.open("1234").is_err(), "{len} bytes");
+    }
+    encryption.wrapped_key = "not base64!".to_string();
+    assert_eq!(
+      encryption.open("1234"),
+      Err("the stored database key is invalid".to_string())
+    );
+  }
+
+  #[test]
+  fn url_never_carries_the_key() {
+    let path = std::env::temp_dir().join("soulsense.db");
+    assert_eq!(database_url(&path), format!("sqlite:///{}", path.display()));
+    assert!(!database_url(&path).contains(&hex(&KEY)));
+  }
+
+  #[test]
+  fn key_goes_to_its_own_variable() {
+    assert_eq!(key_env(None), None);
+    let (name, value) = key_env(Some(&KEY)).unwrap();
+    assert_eq!(name, KEY_ENV_VAR);
+    assert_eq!(value, "07".repeat(KEY_BYTES));
+  }
+}
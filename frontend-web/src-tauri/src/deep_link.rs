@@ -0,0 +1,316 @@
+//! `soulsense://` deep links.
+//!
+//! Links are parsed into a [`DeepLink`] before anything reaches the webview;
+//! anything else, including unknown targets and extra path segments or query
+//! parameters, is rejected. Links arrive as a launch argument on Windows and
+//! Linux, forwarded by [`crate::single_instance`] when the app is already
+//! running, and through the deep-link plugin's open event on macOS.
+
+use tauri::{App, AppHandle, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+use thiserror::Error;
+
+use crate::window::{self, EXAM_START_ROUTE, JOURNAL_NEW_ROUTE};
+
+pub const SCHEME: &str = "soulsense";
+
+/// Longest link accepted, well above any valid one.
+const MAX_LINK_LEN: usize = 2048;
+const MAX_TOKEN_LEN: usize = 512;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeepLink {
+  /// `soulsense://journal/new`
+  NewJournalEntry,
+  /// `soulsense://exam/start`
+  StartExam,
+  /// `soulsense://reset-password?token=...`
+  ResetPassword { token: String },
+  /// `soulsense://assessment/{id}`
+  Assessment { id: u64 },
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DeepLinkError {
+  #[error("link is longer than {MAX_LINK_LEN} bytes")]
+  TooLong,
+  #[error("malformed link: {0}")]
+  Malformed(String),
+  #[error("not a {SCHEME}:// link")]
+  WrongScheme,
+  #[error("unknown link target {0:?}")]
+  Unknown(String),
+  #[error("invalid {0} in link")]
+  Invalid(&'static str),
+}
+
+impl DeepLink {
+  pub fn parse(link: &str) -> Result<Self, DeepLinkError> {
+    if link.len() > MAX_LINK_LEN {
+      return Err(DeepLinkError::TooLong);
+    }
+    // `Url` resolves dot segments, which would hide them from the checks below
+    if has_dot_segment(link) {
+      return Err(DeepLinkError::Malformed("dot segment in path".to_string()));
+    }
+    let url = Url::parse(link).map_err(|e| DeepLinkError::Malformed(e.to_string()))?;
+    if url.scheme() != SCHEME {
+      return Err(DeepLinkError::WrongScheme);
+    }
+    if !url.username().is_empty()
+      || url.password().is_some()
+      || url.port().is_some()
+      || url.fragment().is_some()
+    {
+      return Err(DeepLinkError::Malformed(
+        "unexpected URL component".to_string(),
+      ));
+    }
+
+    let target = url.host_str().unwrap_or_default();
+    let path: Vec<&str> = url
+      .path()
+      .split('/')
+      .filter(|segment| !segment.is_empty())
+      .collect();
+    let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+
+    match (target, path.as_slice(), query.as_slice()) {
+      ("journal", ["new"], []) => Ok(DeepLink::NewJournalEntry),
+      ("exam", ["start"], []) => Ok(DeepLink::StartExam),
+      ("reset-password", [], [(key, token)]) if key == "token" => {
+        if !is_valid_token(token) {
+          return Err(DeepLinkError::Invalid("token"));
+        }
+        Ok(DeepLink::ResetPassword {
+          token: token.clone(),
+        })
+      }
+      ("reset-password", [], _) => Err(DeepLinkError::Invalid("token")),
+      ("assessment", [id], []) => match parse_id(id) {
+        Some(id) => Ok(DeepLink::Assessment { id }),
+        None => Err(DeepLinkError::Invalid("assessment id")),
+      },
+      ("journal" | "exam" | "assessment", _, [_, ..]) => {
+        Err(DeepLinkError::Malformed("unexpected query".to_string()))
+      }
+      _ => Err(DeepLinkError::Unknown(format!("{target}{}", url.path()))),
+    }
+  }
+
+  /// Frontend route the link opens.
+  pub fn route(&self) -> String {
+    match self {
+      DeepLink::NewJournalEntry => JOURNAL_NEW_ROUTE.to_string(),
+      DeepLink::StartExam => EXAM_START_ROUTE.to_string(),
+      DeepLink::ResetPassword { token } => format!("/verify-reset?token={token}"),
+      DeepLink::Assessment { id } => format!("/assessments/{id}"),
+    }
+  }
+}
+
+/// Reset tokens are opaque but URL-safe, so they can go into the route as is.
+fn is_valid_token(token: &str) -> bool {
+  (1..=MAX_TOKEN_LEN).contains(&token.len())
+    && token
+      .bytes()
+      .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~'))
+}
+
+/// Whether the path of `link` has a `.` or `..` segment, plain or encoded.
+fn has_dot_segment(link: &str) -> bool {
+  let path = link.split(['?', '#']).next().unwrap_or_default();
+  path.split(['/', '\\']).any(|segment| {
+    let segment = segment.to_ascii_lowercase().replace("%2e", ".");
+    segment == "." || segment == ".."
+  })
+}
+
+fn parse_id(id: &str) -> Option<u64> {
+  if !id.bytes().all(|b| b.is_ascii_digit()) {
+    return None;
+  }
+  id.parse().ok().filter(|id| *id > 0)
+}
+
+/// Handles links the app was launched with and listens for later ones.
+pub fn init(app: &App) {
+  #[cfg(all(debug_assertions, any(windows, target_os = "linux")))]
+  if let Err(e) = app.deep_link().register_all() {
+    log::warn!("failed to register the {SCHEME}:// scheme: {e}");
+  }
+
+  let handle = app.handle().clone();
+  app.deep_link().on_open_url(move |event| {
+    for url in event.urls() {
+      open(&handle, url.as_str());
+    }
+  });
+
+  open_links(app.handle(), std::env::args().skip(1).collect());
+}
+
+/// Opens every deep link among command line `args` and returns the other
+/// arguments.
+pub fn open_links(app: &AppHandle, args: Vec<String>) -> Vec<String> {
+  let prefix = format!("{SCHEME}:");
+  let (links, rest): (Vec<_>, Vec<_>) = args.into_iter().partition(|arg| {
+    arg
+      .get(..prefix.len())
+      .is_some_and(|start| start.eq_ignore_ascii_case(&prefix))
+  });
+  for link in links {
+    open(app, &link);
+  }
+  rest
+}
+
+fn open(app: &AppHandle, link: &str) {
+  match DeepLink::parse(link) {
+    Ok(link) => {
+      let route = link.route();
+      // Leave out the query, it may carry a token
+      let path = route.split('?').next().unwrap_or_default();
+      log::info!("opening deep link to {path}");
+      window::show_route(app, &route);
+    }
+    Err(e) => log::warn!("rejected deep link: {e}"),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_every_route() {
+    let cases = [
+      (
+        "soulsense://journal/new",
+        DeepLink::NewJournalEntry,
+        "/journal/new",
+      ),
+      ("soulsense://exam/start", DeepLink::StartExam, "/exam/start"),
+      (
+        "soulsense://reset-password?token=abc-123_x.y~z",
+        DeepLink::ResetPassword {
+          token: "abc-123_x.y~z".to_string(),
+        },
+        "/verify-reset?token=abc-123_x.y~z",
+      ),
+      (
+        "soulsense://assessment/42",
+        DeepLink::Assessment { id: 42 },
+        "/assessments/42",
+      ),
+      // Trailing slashes and scheme case do not matter
+      (
+        "SOULSENSE://journal/new/",
+        DeepLink::NewJournalEntry,
+        "/journal/new",
+      ),
+    ];
+    for (link, expected, route) in cases {
+      let parsed = DeepLink::parse(link).unwrap_or_else(|e| panic!("{link}: {e}"));
+      assert_eq!(parsed, expected, "{link}");
+      assert_eq!(parsed.route(), route, "{link}");
+    }
+  }
+
+  #[test]
+  fn rejects_unknown_targets() {
+    for link in [
+      "soulsense://settings",
+      "soulsense://journal",
+      "soulsense://journal/edit",
+      "soulsense://exam/start/now",
+      "soulsense:journal/new",
+    ] {
+      assert!(
+        matches!(DeepLink::parse(link), Err(DeepLinkError::Unknown(_))),
+        "{link}"
+      );
+    }
+    assert_eq!(
+      DeepLink::parse("https://journal/new"),
+      Err(DeepLinkError::WrongScheme)
+    );
+  }
+
+  #[test]
+  fn rejects_path_traversal() {
+    for link in [
+      "soulsense://assessment/..",
+      "soulsense://assessment/%2e%2e",
+      "soulsense://assessment/..%2F..%2Fsettings",
+      "soulsense://assessment/1%2F..",
+      "soulsense://journal/new/../../settings",
+      "soulsense://reset-password/..?token=abc",
+    ] {
+      assert!(DeepLink::parse(link).is_err(), "{link}");
+    }
+  }
+
+  #[test]
+  fn rejects_bad_queries() {
+    for link in [
+      "soulsense://reset-password",
+      "soulsense://reset-password?token=",
+      "soulsense://reset-password?token=a%20b",
+      "soulsense://reset-password?token=abc&next=/settings",
+      "soulsense://reset-password?code=abc",
+    ] {
+      assert_eq!(
+        DeepLink::parse(link),
+        Err(DeepLinkError::Invalid("token")),
+        "{link}"
+      );
+    }
+    for link in [
+      "soulsense://journal/new?draft=1",
+      "soulsense://exam/start?x",
+    ] {
+      assert!(
+        matches!(DeepLink::parse(link), Err(DeepLinkError::Malformed(_))),
+        "{link}"
+      );
+    }
+    let long_token = "a".repeat(MAX_TOKEN_LEN + 1);
+    assert_eq!(
+      DeepLink::parse(&format!("soulsense://reset-password?token={long_token}")),
+      Err(DeepLinkError::Invalid("token"))
+    );
+  }
+
+  #[test]
+  fn rejects_bad_assessment_ids() {
+    for id in ["0", "-1", "+1", "abc", "1.5", "99999999999999999999999"] {
+      assert_eq!(
+        DeepLink::parse(&format!("soulsense://assessment/{id}")),
+        Err(DeepLinkError::Invalid("assessment id")),
+        "{id}"
+      );
+    }
+  }
+
+  #[test]
+  fn rejects_extra_url_components() {
+    for link in [
+      "soulsense://user@journal/new",
+      "soulsense://journal:80/new",
+      "soulsense://journal/new#top",
+    ] {
+      assert!(
+        matches!(DeepLink::parse(link), Err(DeepLinkError::Malformed(_))),
+        "{link}"
+      );
+    }
+    assert_eq!(
+      DeepLink::parse(&format!(
+        "soulsense://journal/new?{}",
+        "a".repeat(MAX_LINK_LEN)
+      )),
+      Err(DeepLinkError::TooLong)
+    );
+  }
+}
@@ -0,0 +1,105 @@
+//! Errors raised while launching the backend sidecar.
+//!
+//! None of these abort the app. [`report`] logs each one and routes it to the
+//! user: problems the user cannot fix by retrying (a missing or blocked
+//! binary) get a native error dialog, the rest are emitted as
+//! [`ERROR_EVENT`] for the in-app error screen, which offers a retry.
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use crate::sidecar_logs::LOG_TARGET;
+
+/// Event emitted to the webview for recoverable sidecar errors.
+pub const ERROR_EVENT: &str = "sidecar://error";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SidecarError {
+  /// The bundled backend binary is not where the bundle says it should be.
+  #[error("the bundled backend is missing: {0}")]
+  MissingBinary(String),
+  /// The binary exists but could not be started, e.g. because it is not
+  /// executable or was blocked by antivirus software.
+  #[error("the backend could not be started: {0}")]
+  Spawn(String),
+  /// The backend exited before it became ready.
+  #[error("the backend exited during startup (exit code: {code:?}, signal: {signal:?})")]
+  EarlyExit {
+    code: Option<i32>,
+    signal: Option<i32>,
+  },
+  /// Another program holds the port the backend wanted.
+  #[error("port {port} is already in use by another program")]
+  PortConflict { port: u16 },
+  /// The database could not be readied for the backend, e.g. because
+  /// encrypting it failed.
+  #[error("the database could not be prepared: {0}")]
+  Database(String),
+}
+
+impl SidecarError {
+  /// Stable identifier for logs and the frontend.
+  pub fn kind(&self) -> &'static str {
+    match self {
+      Self::MissingBinary(_) => "missingBinary",
+      Self::Spawn(_) => "spawn",
+      Self::EarlyExit { .. } => "earlyExit",
+      Self::PortConflict { .. } => "portConflict",
+      Self::Database(_) => "database",
+    }
+  }
+
+  /// Whether retrying is pointless without the user fixing the installation.
+  fn needs_reinstall(&self) -> bool {
+    matches!(self, Self::MissingBinary(_) | Self::Spawn(_))
+  }
+}
+
+impl From<tauri_plugin_shell::Error> for SidecarError {
+  fn from(e: tauri_plugin_shell::Error) -> Self {
+    match e {
+      tauri_plugin_shell::Error::Io(io) if io.kind() == std::io::ErrorKind::NotFound => {
+        Self::MissingBinary(io.to_string())
+      }
+      tauri_plugin_shell::Error::CurrentExeHasNoParent
+      | tauri_plugin_shell::Error::SidecarNotAllowed(_) => Self::MissingBinary(e.to_string()),
+      other => Self::Spawn(other.to_string()),
+    }
+  }
+}
+
+impl Serialize for SidecarError {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut state = serializer.serialize_struct("SidecarError", 2)?;
+    state.serialize_field("kind", self.kind())?;
+    state.serialize_field("message", &self.to_string())?;
+    state.end()
+  }
+}
+
+/// Whether a line of backend output reports that its port is taken.
+pub fn is_port_conflict(line: &[u8]) -> bool {
+  let line = String::from_utf8_lossy(line).to_lowercase();
+  line.contains("address already in use")
+    || line.contains("only one usage of each socket address")
+}
+
+/// Logs `error` and shows it to the user.
+pub fn report(app: &AppHandle, error: &SidecarError) {
+  log::error!(target: LOG_TARGET, "kind={} {error}", error.kind());
+
+  if error.needs_reinstall() {
+    app
+      .dialog()
+      .message(format!(
+        "Soul Sense could not start its local service.\n\n{error}\n\nPlease reinstall the app. If the problem persists, check whether security software is blocking it."
+      ))
+      .title("Soul Sense")
+      .kind(MessageDialogKind::Error)
+      .show(|_| {});
+  } else if let Err(e) = app.emit(ERROR_EVENT, error) {
+    log::warn!("failed to emit {ERROR_EVENT}: {e}");
+  }
+}
@@ -0,0 +1,273 @@
+//! Saving backend export jobs to a file the user picks.
+//!
+//! The webview cannot choose where a download lands, so the shell waits for
+//! the job, asks for a destination in a native save dialog and streams the
+//! file there itself, reporting progress as `export://progress` events.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+use crate::auth;
+use crate::backend::BackendEndpoint;
+use crate::window::MAIN_WINDOW;
+
+pub const PROGRESS_EVENT: &str = "export://progress";
+
+const STATUS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long a job may take to complete before the export is abandoned.
+const JOB_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Downloaded bytes between two progress events.
+const PROGRESS_STEP: u64 = 256 * 1024;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportStage {
+  Waiting,
+  Downloading,
+  Done,
+  Cancelled,
+  Failed,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportProgress {
+  job_id: String,
+  stage: ExportStage,
+  received: u64,
+  total: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct JobStatus {
+  status: String,
+  filename: String,
+}
+
+/// Waits for export `job_id`, asks where to save it and downloads it there.
+///
+/// Returns the saved path, or `None` if the user cancelled the dialog.
+/// Without an `access_token`, the signed-in session's is used.
+#[tauri::command]
+pub async fn export_save(
+  app: AppHandle,
+  job_id: String,
+  access_token: Option<String>,
+) -> Result<Option<PathBuf>, String> {
+  if !is_safe_segment(&job_id) {
+    return Err("invalid export job id".to_string());
+  }
+  let access_token = auth::bearer_token(&app, access_token).await?;
+  let progress = Progress::new(&app, &job_id);
+
+  let result = save(&app, &job_id, &access_token, &progress).await;
+  match &result {
+    Ok(Some(path)) => {
+      log::info!("export {job_id} saved to {}", path.display());
+      progress.emit(ExportStage::Done);
+    }
+    Ok(None) => progress.emit(ExportStage::Cancelled),
+    Err(e) => {
+      log::warn!("export {job_id} failed: {e}");
+      progress.emit(ExportStage::Failed);
+    }
+  }
+  result
+}
+
+async fn save(
+  app: &AppHandle,
+  job_id: &str,
+  access_token: &str,
+  progress: &Progress<'_>,
+) -> Result<Option<PathBuf>, String> {
+  progress.emit(ExportStage::Waiting);
+  let filename = wait_for_job(app, job_id, access_token).await?;
+  let Some(path) = pick_destination(app, &filename).await? else {
+    return Ok(None);
+  };
+
+  // Write next to the destination and move into place once complete, so a
+  // failed download never leaves a truncated file under the chosen name
+  let partial = partial_path(&path);
+  match download(app, &filename, access_token, &partial, progress).await {
+    Ok(()) => {
+      tokio::fs::rename(&partial, &path)
+        .await
+        .map_err(|e| format!("failed to save {}: {e}", path.display()))?;
+      Ok(Some(path))
+    }
+    Err(e) => {
+      let _ = tokio::fs::remove_file(&partial).await;
+      Err(e)
+    }
+  }
+}
+
+/// Polls the job until it completes and returns the file name to download.
+pub(crate) async fn wait_for_job(app: &AppHandle, job_id: &str, access_token: &str) -> Result<String, String> {
+  let deadline = Instant::now() + JOB_TIMEOUT;
+  let path = format!("/api/v1/export/{job_id}/status");
+  loop {
+    let response = app
+      .state::<BackendEndpoint>()
+      .get(&path)
+      .bearer_auth(access_token)
+      .send()
+      .await
+      .map_err(|e| format!("failed to reach the backend: {e}"))?;
+    match response.status() {
+      StatusCode::OK => {}
+      StatusCode::NOT_FOUND => return Err("export job not found".to_string()),
+      status => return Err(format!("export status request failed with {status}")),
+    }
+    let job: JobStatus = response
+      .json()
+      .await
+      .map_err(|e| format!("invalid export status: {e}"))?;
+
+    match job.status.as_str() {
+      "completed" if is_safe_segment(&job.filename) => return Ok(job.filename),
+      "completed" => return Err("backend returned an invalid export file name".to_string()),
+      "failed" | "error" => return Err("the export job failed".to_string()),
+      _ if Instant::now() >= deadline => {
+        return Err(format!(
+          "the export did not complete within {JOB_TIMEOUT:?}"
+        ))
+      }
+      _ => tokio::time::sleep(STATUS_POLL_INTERVAL).await,
+    }
+  }
+}
+
+async fn pick_destination(app: &AppHandle, filename: &str) -> Result<Option<PathBuf>, String> {
+  let mut dialog = app
+    .dialog()
+    .file()
+    .set_title("Save export")
+    .set_file_name(filename);
+  if let Some(extension) = Path::new(filename).extension().and_then(|e| e.to_str()) {
+    dialog = dialog.add_filter(extension.to_uppercase(), &[extension]);
+  }
+  if let Some(main) = app.get_webview_window(MAIN_WINDOW) {
+    dialog = dialog.set_parent(&main);
+  }
+
+  let (tx, rx) = oneshot::channel();
+  dialog.save_file(move |path| {
+    let _ = tx.send(path);
+  });
+  match rx
+    .await
+    .map_err(|_| "the save dialog closed unexpectedly".to_string())?
+  {
+    Some(path) => path.into_path().map(Some).map_err(|e| e.to_string()),
+    None => Ok(None),
+  }
+}
+
+async fn download(
+  app: &AppHandle,
+  filename: &str,
+  access_token: &str,
+  destination: &Path,
+  progress: &Progress<'_>,
+) -> Result<(), String> {
+  let mut response = app
+    .state::<BackendEndpoint>()
+    .get(&format!("/api/v1/export/{filename}/download"))
+    .bearer_auth(access_token)
+    .timeout(DOWNLOAD_TIMEOUT)
+    .send()
+    .await
+    .map_err(|e| format!("failed to reach the backend: {e}"))?;
+  if !response.status().is_success() {
+    return Err(format!("export download failed with {}", response.status()));
+  }
+
+  let total = response.content_length();
+  let mut file = tokio::fs::File::create(destination)
+    .await
+    .map_err(|e| format!("failed to create {}: {e}", destination.display()))?;
+  let mut received = 0;
+  let mut reported = 0;
+  progress.downloading(received, total);
+  while let Some(chunk) = response
+    .chunk()
+    .await
+    .map_err(|e| format!("export download interrupted: {e}"))?
+  {
+    file
+      .write_all(&chunk)
+      .await
+      .map_err(|e| format!("failed to write {}: {e}", destination.display()))?;
+    received += chunk.len() as u64;
+    if received - reported >= PROGRESS_STEP {
+      progress.downloading(received, total);
+      reported = received;
+    }
+  }
+  file
+    .sync_all()
+    .await
+    .map_err(|e| format!("failed to write {}: {e}", destination.display()))?;
+  progress.downloading(received, total);
+  Ok(())
+}
+
+/// Job ids and file names come back from the backend and end up in request
+/// paths, so only plain names are accepted.
+pub(crate) fn is_safe_segment(segment: &str) -> bool {
+  !segment.is_empty()
+    && !segment.starts_with('.')
+    && segment
+      .bytes()
+      .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+  let mut name = path.file_name().unwrap_or_default().to_os_string();
+  name.push(".part");
+  path.with_file_name(name)
+}
+
+/// Emits progress events for one export.
+struct Progress<'a> {
+  app: &'a AppHandle,
+  job_id: &'a str,
+}
+
+impl<'a> Progress<'a> {
+  fn new(app: &'a AppHandle, job_id: &'a str) -> Self {
+    Self { app, job_id }
+  }
+
+  fn emit(&self, stage: ExportStage) {
+    self.send(stage, 0, None);
+  }
+
+  fn downloading(&self, received: u64, total: Option<u64>) {
+    self.send(ExportStage::Downloading, received, total);
+  }
+
+  fn send(&self, stage: ExportStage, received: u64, total: Option<u64>) {
+    let progress = ExportProgress {
+      job_id: self.job_id.to_string(),
+      stage,
+      received,
+      total,
+    };
+    if let Err(e) = self.app.emit(PROGRESS_EVENT, progress) {
+      log::warn!("failed to emit {PROGRESS_EVENT}: {e}");
+    }
+  }
+}
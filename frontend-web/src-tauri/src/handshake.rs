@@ -0,0 +1,45 @@
+//! Shared-secret handshake between the shell and the sidecar.
+//!
+//! The shell generates a random token on every launch and hands it to the
+//! backend in [`TOKEN_ENV_VAR`]. The backend rejects any request that does not
+//! carry it in [`TOKEN_HEADER`], so other local programs cannot reach the API
+//! on its loopback port.
+
+use std::fmt;
+
+/// Environment variable the sidecar reads the token from.
+pub const TOKEN_ENV_VAR: &str = "SOULSENSE_SIDECAR_TOKEN";
+
+/// Header every request to the sidecar must carry.
+pub const TOKEN_HEADER: &str = "X-SoulSense-Token";
+
+const TOKEN_BYTES: usize = 32;
+
+/// Per-launch secret shared with the sidecar.
+#[derive(Clone)]
+pub struct SidecarToken(String);
+
+impl SidecarToken {
+  /// Generates a fresh token from the OS random number generator.
+  pub fn generate() -> Result<Self, getrandom::Error> {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    getrandom::fill(&mut bytes)?;
+    Ok(Self(bytes.iter().map(|b| format!("{b:02x}")).collect()))
+  }
+
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+
+  /// Attaches the token header to a request bound for the sidecar.
+  pub fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    request.header(TOKEN_HEADER, self.as_str())
+  }
+}
+
+// Keep the secret out of logs.
+impl fmt::Debug for SidecarToken {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("SidecarToken(..)")
+  }
+}
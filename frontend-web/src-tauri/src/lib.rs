@@ -1,25 +1,148 @@
+use tauri::{Manager, RunEvent, WindowEvent};
+
+mod app_lock;
+mod auth;
+mod backend;
+mod csp;
+mod database;
+mod deep_link;
+mod error;
+mod export;
+mod handshake;
+mod logging;
+mod menu;
+mod notify;
+mod paths;
+mod proxy;
+mod readiness;
+mod reminders;
+mod settings;
+mod sidecar;
+mod sidecar_logs;
+mod single_instance;
+mod token_store;
+mod tray;
+mod util;
+mod window;
+mod window_state;
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+  let mut context = tauri::generate_context!();
+  csp::apply(&mut context);
+
   tauri::Builder::default()
     .plugin(tauri_plugin_shell::init())
+    .plugin(tauri_plugin_deep_link::init())
+    .plugin(tauri_plugin_dialog::init())
+    .plugin(tauri_plugin_notification::init())
+    .plugin(tauri_plugin_opener::init())
+    .register_asynchronous_uri_scheme_protocol(proxy::SCHEME, proxy::handle)
+    .manage(sidecar::SidecarState::default())
+    .manage(readiness::ReadinessState::default())
+    .manage(sidecar_logs::SidecarLogs::default())
+    .manage(notify::Notifier::default())
+    .invoke_handler(tauri::generate_handler![
+      app_lock::lock_activity,
+      app_lock::lock_disable,
+      app_lock::lock_now,
+      app_lock::lock_set_idle_minutes,
+      app_lock::lock_set_pin,
+      app_lock::lock_status,
+      app_lock::lock_unlock,
+      auth::auth_clear,
+      auth::auth_get_access_token,
+      auth::auth_store_tokens,
+      database::database_encrypt,
+      database::database_status,
+      export::export_save,
+      proxy::api_base_url,
+      logging::log_level,
+      logging::set_log_level,
+      notify::do_not_disturb,
+      notify::notify,
+      notify::set_do_not_disturb,
+      notify::watch_export,
+      readiness::readiness_retry,
+      readiness::readiness_state,
+      reminders::reminder_settings,
+      reminders::set_reminder_settings,
+      reminders::snooze_reminder,
+      sidecar::sidecar_status,
+      sidecar_logs::sidecar_logs,
+      tray::close_to_tray,
+      tray::set_close_to_tray
+    ])
     .setup(|app| {
-      if cfg!(debug_assertions) {
-        app.handle().plugin(
-          tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Info)
-            .build(),
-        )?;
+      // Create the app's directories before anything writes to them
+      let dirs = paths::AppDirs::resolve(app.handle())?;
+      dirs.create()?;
+      app.manage(dirs);
+
+      app.manage(settings::SettingsStore::load(app.handle())?);
+      app.handle().plugin(logging::plugin())?;
+      logging::init(app.handle());
+
+      // Hand over to an already running instance before starting anything
+      single_instance::enforce(app);
+
+      // Pick the backend transport and a per-launch token, then open the
+      // main window, which reaches the backend through the proxy scheme. It
+      // stays hidden behind the splash until the backend reports ready
+      let token = handshake::SidecarToken::generate()?;
+      let socket_dir = paths::app_runtime_dir(app.handle());
+      app.manage(backend::BackendEndpoint::new(socket_dir.as_deref(), token)?);
+      // Keep the login session out of the webview, refreshing it ahead of
+      // expiry
+      app.manage(auth::AuthState::new(app.handle())?);
+      auth::start(app.handle());
+      app.manage(window_state::WindowStateStore::load(app.handle())?);
+      app.manage(app_lock::AppLock::load(app.handle())?);
+      app.manage(database::Database::load(&app.state::<paths::AppDirs>()));
+      window::create_main_window(app)?;
+      readiness::create_splash_window(app)?;
+
+      // Offer quick actions from the menu bar and the tray, which can
+      // optionally keep the app running in the background
+      menu::create(app)?;
+      tray::create(app)?;
+
+      // Deliver check-in reminders from the shell, even while hidden
+      let reminder_settings = app.state::<settings::SettingsStore>().get().reminders;
+      app.manage(reminders::Reminders::new(reminder_settings));
+      reminders::start(app.handle());
+
+      // Open the soulsense:// link the app was launched with, if any
+      deep_link::init(app);
+
+      // Lock the app behind its PIN after a period of inactivity
+      app_lock::start(app.handle());
+
+      // Make sure the backend never outlives the shell, even after a panic
+      sidecar::install_panic_hook(app.handle());
+
+      // Start the Python sidecar under supervision. An encrypted database
+      // needs the PIN first, so the backend then starts on unlock
+      if app.state::<database::Database>().needs_key() {
+        app_lock::present(app.handle(), None);
+      } else {
+        sidecar::start(app.handle());
       }
 
-      // Start the Python sidecar
-      use tauri_plugin_shell::ShellExt;
-      let sidecar_command = app.shell().sidecar("soul-sense-backend").unwrap();
-      let (_rx, _child) = sidecar_command
-          .spawn()
-          .expect("Failed to spawn sidecar");
-      
       Ok(())
     })
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(context)
+    .expect("error while building tauri application")
+    .run(|app, event| match event {
+      RunEvent::WindowEvent {
+        event: WindowEvent::Destroyed,
+        ..
+      } if app.webview_windows().is_empty() => sidecar::shutdown(app),
+      RunEvent::ExitRequested { .. } => sidecar::shutdown(app),
+      RunEvent::Exit => {
+        window_state::save_all(app);
+        sidecar::shutdown(app);
+      }
+      _ => {}
+    });
 }
@@ -0,0 +1,80 @@
+//! Application logging.
+//!
+//! Logs go to stdout and to a size-rotated file in the app log dir, in both
+//! debug and release builds, so installed builds produce something to attach
+//! to a bug report. The level comes from `SOULSENSE_LOG_LEVEL`, then from the
+//! settings file, and can be changed at runtime with [`set_log_level`].
+
+use std::str::FromStr;
+
+use log::LevelFilter;
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+
+use crate::settings::SettingsStore;
+
+/// Environment variable overriding the configured log level.
+pub const LEVEL_ENV_VAR: &str = "SOULSENSE_LOG_LEVEL";
+
+const LOG_FILE_NAME: &str = "soul-sense";
+/// Size at which the log file is rotated.
+const MAX_FILE_SIZE: u128 = 5 * 1024 * 1024;
+/// Rotated files kept next to the active one.
+const KEEP_FILES: usize = 5;
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+
+/// Builds the log plugin.
+///
+/// The plugin itself lets every record through; the effective level is the
+/// global `log` max level, which [`init`] and [`set_log_level`] adjust.
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+  tauri_plugin_log::Builder::default()
+    .clear_targets()
+    .targets([
+      Target::new(TargetKind::Stdout),
+      Target::new(TargetKind::LogDir {
+        file_name: Some(LOG_FILE_NAME.into()),
+      }),
+    ])
+    .max_file_size(MAX_FILE_SIZE)
+    .rotation_strategy(RotationStrategy::KeepSome(KEEP_FILES))
+    .level(LevelFilter::Trace)
+    .build()
+}
+
+/// Applies the configured level. Must run after the plugin is registered.
+pub fn init(app: &AppHandle) {
+  let configured = app.state::<SettingsStore>().get().log_level;
+  let level = match std::env::var(LEVEL_ENV_VAR) {
+    Ok(value) => parse_level(&value).unwrap_or_else(|| {
+      log::warn!("ignoring invalid {LEVEL_ENV_VAR}={value:?}");
+      DEFAULT_LEVEL
+    }),
+    Err(_) => configured
+      .as_deref()
+      .and_then(parse_level)
+      .unwrap_or(DEFAULT_LEVEL),
+  };
+  log::set_max_level(level);
+  log::info!("log level set to {level}");
+}
+
+fn parse_level(value: &str) -> Option<LevelFilter> {
+  LevelFilter::from_str(value.trim()).ok()
+}
+
+/// Returns the active log level.
+#[tauri::command]
+pub fn log_level() -> String {
+  log::max_level().as_str().to_lowercase()
+}
+
+/// Changes the log level immediately and remembers it for future launches.
+#[tauri::command]
+pub fn set_log_level(settings: tauri::State<'_, SettingsStore>, level: String) -> Result<(), String> {
+  let filter = parse_level(&level).ok_or_else(|| format!("unknown log level {level:?}"))?;
+  log::set_max_level(filter);
+  log::info!("log level changed to {filter}");
+  settings.update(|s| s.log_level = Some(filter.as_str().to_lowercase()))?;
+  Ok(())
+}
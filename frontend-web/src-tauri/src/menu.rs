@@ -0,0 +1,201 @@
+//! Native application menu.
+//!
+//! Items the shell can handle on its own (quitting, reloading, zoom, opening
+//! folders) are performed here; the rest are emitted to the main window as
+//! `menu://action` events for the frontend to route on.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tauri::menu::{AboutMetadata, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::{App, AppHandle, Emitter, Manager, WebviewWindow};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::util::lock;
+use crate::window::{self, JOURNAL_NEW_ROUTE, MAIN_WINDOW};
+
+pub const MENU_EVENT: &str = "menu://action";
+
+const NEW_JOURNAL_ENTRY: &str = "file.new-journal-entry";
+const EXPORT_DATA: &str = "file.export-data";
+const QUIT: &str = "file.quit";
+const RELOAD: &str = "view.reload";
+const ZOOM_IN: &str = "view.zoom-in";
+const ZOOM_OUT: &str = "view.zoom-out";
+const ZOOM_RESET: &str = "view.zoom-reset";
+#[cfg(debug_assertions)]
+const TOGGLE_DEVTOOLS: &str = "view.toggle-devtools";
+const SHOW_LOGS: &str = "help.show-logs";
+const OPEN_DATA_FOLDER: &str = "help.open-data-folder";
+
+const ZOOM_STEP: f64 = 0.1;
+const ZOOM_MIN: f64 = 0.5;
+const ZOOM_MAX: f64 = 2.0;
+
+/// Zoom factor applied to every window from the View menu.
+pub struct MenuState {
+  zoom: Mutex<f64>,
+}
+
+/// Builds the menu and attaches it to the main window (the app menu bar on
+/// macOS), so the splash and quick journal windows stay menu-less.
+pub fn create(app: &App) -> tauri::Result<()> {
+  let about = AboutMetadata {
+    name: Some(app.package_info().name.clone()),
+    version: Some(app.package_info().version.to_string()),
+    ..Default::default()
+  };
+
+  let file = Submenu::with_items(
+    app,
+    "&File",
+    true,
+    &[
+      &MenuItem::with_id(
+        app,
+        NEW_JOURNAL_ENTRY,
+        "New journal entry",
+        true,
+        Some("CmdOrCtrl+N"),
+      )?,
+      &MenuItem::with_id(app, EXPORT_DATA, "Export data…", true, Some("CmdOrCtrl+E"))?,
+      &PredefinedMenuItem::separator(app)?,
+      &MenuItem::with_id(app, QUIT, "Quit", true, Some("CmdOrCtrl+Q"))?,
+    ],
+  )?;
+  // Without an Edit menu macOS does not route clipboard shortcuts to the
+  // webview
+  let edit = Submenu::with_items(
+    app,
+    "&Edit",
+    true,
+    &[
+      &PredefinedMenuItem::undo(app, None)?,
+      &PredefinedMenuItem::redo(app, None)?,
+      &PredefinedMenuItem::separator(app)?,
+      &PredefinedMenuItem::cut(app, None)?,
+      &PredefinedMenuItem::copy(app, None)?,
+      &PredefinedMenuItem::paste(app, None)?,
+      &PredefinedMenuItem::select_all(app, None)?,
+    ],
+  )?;
+  let view = Submenu::with_items(
+    app,
+    "&View",
+    true,
+    &[
+      &MenuItem::with_id(app, RELOAD, "Reload", true, Some("CmdOrCtrl+R"))?,
+      &PredefinedMenuItem::separator(app)?,
+      &MenuItem::with_id(app, ZOOM_IN, "Zoom in", true, Some("CmdOrCtrl+="))?,
+      &MenuItem::with_id(app, ZOOM_OUT, "Zoom out", true, Some("CmdOrCtrl+-"))?,
+      &MenuItem::with_id(app, ZOOM_RESET, "Actual size", true, Some("CmdOrCtrl+0"))?,
+    ],
+  )?;
+  #[cfg(debug_assertions)]
+  view.append_items(&[
+    &PredefinedMenuItem::separator(app)?,
+    &MenuItem::with_id(
+      app,
+      TOGGLE_DEVTOOLS,
+      "Toggle developer tools",
+      true,
+      Some("CmdOrCtrl+Alt+I"),
+    )?,
+  ])?;
+  let help = Submenu::with_items(
+    app,
+    "&Help",
+    true,
+    &[
+      &MenuItem::with_id(app, SHOW_LOGS, "Show logs", true, None::<&str>)?,
+      &MenuItem::with_id(app, OPEN_DATA_FOLDER, "Open data folder", true, None::<&str>)?,
+      &PredefinedMenuItem::separator(app)?,
+      &PredefinedMenuItem::about(app, Some("About Soul Sense"), Some(about))?,
+    ],
+  )?;
+  let menu = Menu::with_items(app, &[&file, &edit, &view, &help])?;
+
+  app.manage(MenuState {
+    zoom: Mutex::new(1.0),
+  });
+  app.on_menu_event(on_menu_event);
+
+  #[cfg(target_os = "macos")]
+  app.set_menu(menu)?;
+  #[cfg(not(target_os = "macos"))]
+  if let Some(main) = app.get_webview_window(MAIN_WINDOW) {
+    main.set_menu(menu)?;
+  }
+  Ok(())
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+  match event.id().as_ref() {
+    NEW_JOURNAL_ENTRY => window::show_route(app, JOURNAL_NEW_ROUTE),
+    EXPORT_DATA => emit_action(app, "exportData"),
+    QUIT => app.exit(0),
+    RELOAD => {
+      if let Some(window) = focused_window(app) {
+        let _ = window.reload();
+      }
+    }
+    ZOOM_IN => zoom(app, |zoom| zoom + ZOOM_STEP),
+    ZOOM_OUT => zoom(app, |zoom| zoom - ZOOM_STEP),
+    ZOOM_RESET => zoom(app, |_| 1.0),
+    #[cfg(debug_assertions)]
+    TOGGLE_DEVTOOLS => {
+      if let Some(window) = focused_window(app) {
+        if window.is_devtools_open() {
+          window.close_devtools();
+        } else {
+          window.open_devtools();
+        }
+      }
+    }
+    SHOW_LOGS => open_folder(app, app.path().app_log_dir()),
+    OPEN_DATA_FOLDER => open_folder(app, app.path().app_data_dir()),
+    _ => {}
+  }
+}
+
+/// Asks the frontend to handle `action`, bringing the main window forward.
+fn emit_action(app: &AppHandle, action: &str) {
+  window::focus_main_window(app);
+  if let Err(e) = app.emit_to(MAIN_WINDOW, MENU_EVENT, action) {
+    log::warn!("failed to emit {MENU_EVENT}: {e}");
+  }
+}
+
+/// The window the user is looking at, falling back to the main window.
+fn focused_window(app: &AppHandle) -> Option<WebviewWindow> {
+  app
+    .webview_windows()
+    .into_values()
+    .find(|window| window.is_focused().unwrap_or(false))
+    .or_else(|| app.get_webview_window(MAIN_WINDOW))
+}
+
+fn zoom(app: &AppHandle, change: impl FnOnce(f64) -> f64) {
+  let state = app.state::<MenuState>();
+  let mut zoom = lock(&state.zoom);
+  *zoom = change(*zoom).clamp(ZOOM_MIN, ZOOM_MAX);
+  for window in app.webview_windows().values() {
+    let _ = window.set_zoom(*zoom);
+  }
+}
+
+fn open_folder(app: &AppHandle, dir: tauri::Result<PathBuf>) {
+  let result = dir
+    .map_err(|e| e.to_string())
+    .and_then(|dir| fs::create_dir_all(&dir).map(|_| dir).map_err(|e| e.to_string()))
+    .and_then(|dir| {
+      app
+        .opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| e.to_string())
+    });
+  if let Err(e) = result {
+    log::warn!("failed to open folder: {e}");
+  }
+}
@@ -0,0 +1,165 @@
+//! OS notifications for backend outcomes and reminders.
+//!
+//! Every notification goes through [`show`], which drops it while "do not
+//! disturb" is on, throttles each category, and skips backend outcomes the
+//! user can already see because a window is focused.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::auth;
+use crate::export;
+use crate::settings::SettingsStore;
+use crate::util::lock;
+
+const MAX_TITLE_LEN: usize = 120;
+const MAX_BODY_LEN: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Category {
+  ExportReady,
+  PasswordReset,
+  AssessmentResult,
+  Reminder,
+}
+
+impl Category {
+  /// Shortest time between two notifications of this category.
+  fn throttle(self) -> Duration {
+    match self {
+      Category::ExportReady => Duration::from_secs(10),
+      Category::PasswordReset => Duration::from_secs(60),
+      Category::AssessmentResult => Duration::from_secs(30),
+      // Rules are few and already spaced out by the scheduler
+      Category::Reminder => Duration::ZERO,
+    }
+  }
+
+  /// Whether the notification is only worth showing while the app is in the
+  /// background.
+  fn background_only(self) -> bool {
+    self != Category::Reminder
+  }
+}
+
+#[derive(Default)]
+pub struct Notifier {
+  last_shown: Mutex<HashMap<Category, Instant>>,
+}
+
+impl Notifier {
+  /// Records a notification of `category` unless one was shown too recently.
+  fn admit(&self, category: Category) -> bool {
+    let mut last_shown = lock(&self.last_shown);
+    let now = Instant::now();
+    match last_shown.get(&category) {
+      Some(last) if now.duration_since(*last) < category.throttle() => false,
+      _ => {
+        last_shown.insert(category, now);
+        true
+      }
+    }
+  }
+}
+
+/// Shows a notification if the user's settings and throttling allow it, and
+/// returns whether it was shown.
+pub fn show(app: &AppHandle, category: Category, title: &str, body: &str) -> bool {
+  if app.state::<SettingsStore>().get().do_not_disturb {
+    log::debug!("notify: {category:?} suppressed by do not disturb");
+    return false;
+  }
+  if category.background_only() && has_focus(app) {
+    return false;
+  }
+  if !app.state::<Notifier>().admit(category) {
+    log::debug!("notify: {category:?} throttled");
+    return false;
+  }
+
+  match app.notification().builder().title(title).body(body).show() {
+    Ok(()) => true,
+    Err(e) => {
+      log::warn!("failed to show {category:?} notification: {e}");
+      false
+    }
+  }
+}
+
+fn has_focus(app: &AppHandle) -> bool {
+  app
+    .webview_windows()
+    .values()
+    .any(|window| window.is_focused().unwrap_or(false))
+}
+
+/// Shows a notification for a backend outcome; returns whether it was shown.
+#[tauri::command]
+pub fn notify(
+  app: AppHandle,
+  category: Category,
+  title: String,
+  body: String,
+) -> Result<bool, String> {
+  if title.trim().is_empty() || title.len() > MAX_TITLE_LEN {
+    return Err(format!("title must be 1 to {MAX_TITLE_LEN} bytes"));
+  }
+  if body.len() > MAX_BODY_LEN {
+    return Err(format!("body must be at most {MAX_BODY_LEN} bytes"));
+  }
+  Ok(show(&app, category, &title, &body))
+}
+
+/// Returns whether notifications are silenced.
+#[tauri::command]
+pub fn do_not_disturb(settings: tauri::State<'_, SettingsStore>) -> bool {
+  settings.get().do_not_disturb
+}
+
+/// Silences or restores notifications, and persists the choice.
+#[tauri::command]
+pub fn set_do_not_disturb(
+  settings: tauri::State<'_, SettingsStore>,
+  enabled: bool,
+) -> Result<(), String> {
+  settings.update(|settings| settings.do_not_disturb = enabled)?;
+  log::info!(
+    "do not disturb {}",
+    if enabled { "enabled" } else { "disabled" }
+  );
+  Ok(())
+}
+
+/// Watches export `job_id` in the background and notifies when it is ready.
+/// Without an `access_token`, the signed-in session's is used.
+#[tauri::command]
+pub async fn watch_export(
+  app: AppHandle,
+  job_id: String,
+  access_token: Option<String>,
+) -> Result<(), String> {
+  if !export::is_safe_segment(&job_id) {
+    return Err("invalid export job id".to_string());
+  }
+  let access_token = auth::bearer_token(&app, access_token).await?;
+  tauri::async_runtime::spawn(async move {
+    match export::wait_for_job(&app, &job_id, &access_token).await {
+      Ok(filename) => {
+        show(
+          &app,
+          Category::ExportReady,
+          "Export ready",
+          &format!("{filename} is ready to save."),
+        );
+      }
+      Err(e) => log::warn!("export {job_id} failed: {e}"),
+    }
+  });
+  Ok(())
+}
@@ -0,0 +1,187 @@
+//! Per-app directories resolved through Tauri's path resolver.
+//!
+//! [`AppDirs`] holds the data, config, cache and log dirs the shell and the
+//! backend share. They are created private to the user at startup and
+//! handed to the sidecar as absolute paths, so the backend never depends on
+//! its working directory.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Manager};
+
+/// File name of the backend database in the data dir.
+const DATABASE_FILE: &str = "soulsense.db";
+/// File name of the backend log in the log dir, next to the shell's.
+const BACKEND_LOG_FILE: &str = "soul-sense-backend.log";
+
+/// Directories the shell and the backend keep their files in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppDirs {
+  pub data: PathBuf,
+  pub config: PathBuf,
+  pub cache: PathBuf,
+  pub log: PathBuf,
+}
+
+impl AppDirs {
+  /// Resolves the platform's per-app directories.
+  pub fn resolve(app: &AppHandle) -> tauri::Result<Self> {
+    let path = app.path();
+    Ok(Self {
+      data: path.app_data_dir()?,
+      config: path.app_config_dir()?,
+      cache: path.app_cache_dir()?,
+      log: path.app_log_dir()?,
+    })
+  }
+
+  /// Lays the directories out under a single `base` dir.
+  #[cfg(test)]
+  pub fn from_base(base: &Path) -> Self {
+    Self {
+      data: base.join("data"),
+      config: base.join("config"),
+      cache: base.join("cache"),
+      log: base.join("logs"),
+    }
+  }
+
+  /// Creates the directories, readable by the current user only. On Windows
+  /// they live in the user's profile, which is private already.
+  pub fn create(&self) -> io::Result<()> {
+    for dir in [&self.data, &self.config, &self.cache, &self.log] {
+      create_private_dir(dir)?;
+    }
+    Ok(())
+  }
+
+  pub fn database_path(&self) -> PathBuf {
+    self.data.join(DATABASE_FILE)
+  }
+
+  pub fn backend_log_path(&self) -> PathBuf {
+    self.log.join(BACKEND_LOG_FILE)
+  }
+
+  /// Environment variables for the sidecar process, pointing it at these
+  /// directories and at the database through `database_url`.
+  pub fn sidecar_env(&self, database_url: String) -> [(&'static str, String); 5] {
+    let path = |path: &Path| path.display().to_string();
+    [
+      ("SOULSENSE_DATA_DIR", path(&self.data)),
+      ("SOULSENSE_CONFIG_DIR", path(&self.config)),
+      ("SOULSENSE_CACHE_DIR", path(&self.cache)),
+      ("SOULSENSE_LOG_FILE", path(&self.backend_log_path())),
+      ("DATABASE_URL", database_url),
+    ]
+  }
+}
+
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    fs::DirBuilder::new()
+      .recursive(true)
+      .mode(0o700)
+      .create(dir)?;
+    // Tighten dirs an earlier version created with the default mode
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+  }
+  #[cfg(not(unix))]
+  fs::create_dir_all(dir)
+}
+
+/// Directory for sockets and other runtime files, if the platform has one.
+///
+/// Only Linux provides a per-user runtime dir (`$XDG_RUNTIME_DIR`), which is
+/// private to the user and cleared on logout.
+pub fn app_runtime_dir(app: &AppHandle) -> Option<PathBuf> {
+  let runtime_dir = app.path().runtime_dir().ok()?;
+  Some(runtime_dir.join(&app.config().identifier))
+}
+
+/// Directory holding the single-instance lock and socket.
+pub fn instance_dir(app: &AppHandle) -> Option<PathBuf> {
+  app_runtime_dir(app).or_else(|| app.path().app_local_data_dir().ok())
+}
+
+/// Writes `contents` readable by the current user only, replacing `path` in
+/// one step.
+pub fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir)?;
+  }
+  let partial = path.with_extension("tmp");
+  let _ = fs::remove_file(&partial);
+
+  let mut options = fs::OpenOptions::new();
+  options.write(true).create_new(true);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+  }
+  let mut file = options.open(&partial)?;
+  file.write_all(contents)?;
+  file.sync_all()?;
+  fs::rename(&partial, path)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use super::*;
+  use crate::database::database_url;
+
+  #[test]
+  fn sidecar_env_points_into_the_base_dir() {
+    let base = std::env::temp_dir().join("soulsense-paths-test");
+    let dirs = AppDirs::from_base(&base);
+    let database = base.join("data").join(DATABASE_FILE);
+    let env: HashMap<_, _> = dirs
+      .sidecar_env(database_url(&dirs.database_path()))
+      .into_iter()
+      .collect();
+
+    let dir = |name: &str| base.join(name).display().to_string();
+    assert_eq!(env["SOULSENSE_DATA_DIR"], dir("data"));
+    assert_eq!(env["SOULSENSE_CONFIG_DIR"], dir("config"));
+    assert_eq!(env["SOULSENSE_CACHE_DIR"], dir("cache"));
+    assert_eq!(
+      env["SOULSENSE_LOG_FILE"],
+      base
+        .join("logs")
+        .join(BACKEND_LOG_FILE)
+        .display()
+        .to_string()
+    );
+    assert_eq!(
+      env["DATABASE_URL"],
+      format!("sqlite:///{}", database.display())
+    );
+    for path in env.values().filter(|value| !value.starts_with("sqlite:")) {
+      assert!(Path::new(path).is_absolute(), "{path} is relative");
+    }
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn dirs_are_created_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let base = std::env::temp_dir().join(format!("soulsense-paths-{}", std::process::id()));
+    let dirs = AppDirs::from_base(&base);
+    dirs.create().unwrap();
+
+    for dir in [&dirs.data, &dirs.config, &dirs.cache, &dirs.log] {
+      let mode = fs::metadata(dir).unwrap().permissions().mode();
+      assert_eq!(mode & 0o777, 0o700, "{}", dir.display());
+    }
+    fs::remove_dir_all(&base).unwrap();
+  }
+}
@@ -0,0 +1,352 @@
+//! `soulsense-api://` scheme that proxies webview API calls to the sidecar.
+//!
+//! The frontend never sees the sidecar's port or token. Requests to the
+//! scheme are checked against an allowlist of v1 router prefixes, forwarded
+//! with the handshake header attached, and answered with the backend's
+//! response. Only the app's own pages may call it, and cookies the auth
+//! routes set are dropped, since the refresh token stays in the shell (see
+//! [`crate::auth`]). Swapping the transport to the backend only touches
+//! [`BackendEndpoint`], not the frontend.
+
+use std::time::Duration;
+
+use tauri::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use tauri::http::{Method, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, UriSchemeContext, UriSchemeResponder};
+
+use crate::backend::BackendEndpoint;
+use crate::handshake::TOKEN_HEADER;
+
+pub const SCHEME: &str = "soulsense-api";
+
+/// Origin under which the webview sees the scheme.
+#[cfg(any(windows, target_os = "android"))]
+const ORIGIN: &str = "http://soulsense-api.localhost";
+#[cfg(not(any(windows, target_os = "android")))]
+const ORIGIN: &str = "soulsense-api://localhost";
+
+/// Origins the app's own pages are served from: `tauri://localhost`, or
+/// `http(s)://tauri.localhost` on Windows and Android.
+const APP_ORIGINS: &[&str] = &[
+  "tauri://localhost",
+  "http://tauri.localhost",
+  "https://tauri.localhost",
+];
+
+const API_PREFIX: &str = "/api/v1";
+const AUTH_ROUTE: &str = "/auth";
+
+/// Routers mounted on the v1 API (see `api/api/v1/router.py`), plus the
+/// health probes it exposes at its root.
+const ALLOWED_ROUTES: &[&str] = &[
+  "/auth",
+  "/users",
+  "/profiles",
+  "/assessments",
+  "/exams",
+  "/questions",
+  "/analytics",
+  "/journal",
+  "/sync",
+  "/community",
+  "/contact",
+  "/export",
+  "/deep-dive",
+  "/health",
+  "/ready",
+  "/startup",
+];
+
+/// Proxied calls include exports and analytics, so they get far more time
+/// than the shell's own calls to the backend.
+const PROXY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Largest backend response the proxy buffers. Full exports stay well below
+/// this; anything larger is answered with `502` instead of growing the
+/// shell's memory without bound.
+const MAX_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Request headers that only make sense on the webview's side of the proxy.
+const DROPPED_REQUEST_HEADERS: &[HeaderName] = &[
+  header::HOST,
+  header::ORIGIN,
+  header::REFERER,
+  header::CONNECTION,
+  header::CONTENT_LENGTH,
+  header::TRANSFER_ENCODING,
+];
+
+/// Response headers the proxy rewrites or that do not survive buffering.
+const DROPPED_RESPONSE_HEADERS: &[HeaderName] = &[
+  header::CONNECTION,
+  header::CONTENT_LENGTH,
+  header::TRANSFER_ENCODING,
+  header::ACCESS_CONTROL_ALLOW_ORIGIN,
+  header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+];
+
+/// Base URL of the v1 API as seen from the webview.
+fn webview_api_url() -> String {
+  format!("{ORIGIN}{API_PREFIX}")
+}
+
+/// Script run in every page before the frontend loads, exposing the API URL
+/// as `window.__SOULSENSE_API_URL__`.
+pub fn initialization_script() -> String {
+  let url = serde_json::Value::String(webview_api_url());
+  format!("window.__SOULSENSE_API_URL__ = {url};")
+}
+
+/// Returns the base URL of the v1 API as seen from the webview.
+#[tauri::command]
+pub fn api_base_url() -> String {
+  webview_api_url()
+}
+
+/// Handler passed to `register_asynchronous_uri_scheme_protocol`.
+pub fn handle(ctx: UriSchemeContext<'_, tauri::Wry>, request: Request<Vec<u8>>, responder: UriSchemeResponder) {
+  let app = ctx.app_handle().clone();
+  tauri::async_runtime::spawn(async move {
+    let origin = request.headers().get(header::ORIGIN).cloned();
+    let dev_origin = dev_origin(&app);
+    let presented = origin
+      .as_ref()
+      .map(|origin| origin.to_str().unwrap_or_default());
+    if !origin_allowed(presented, dev_origin.as_deref()) {
+      log::warn!("proxy: rejected request from origin {origin:?}");
+      responder.respond(error(StatusCode::FORBIDDEN, "origin is not allowed"));
+      return;
+    }
+    let mut response = if request.method() == Method::OPTIONS {
+      preflight(&request)
+    } else {
+      forward(&app, request).await
+    };
+    allow_origin(response.headers_mut(), origin);
+    responder.respond(response);
+  });
+}
+
+/// Origin of the dev server, which serves the pages in debug builds.
+fn dev_origin(app: &AppHandle) -> Option<String> {
+  if !cfg!(debug_assertions) {
+    return None;
+  }
+  let url = app.config().build.dev_url.as_ref()?;
+  Some(url.origin().ascii_serialization())
+}
+
+/// Whether a request with the given `Origin` header may use the proxy: one
+/// of the app's pages, or the dev server when `dev_origin` is set.
+///
+/// A missing `Origin` is allowed on purpose. The scheme is only reachable
+/// from the app's own webviews, and webviews omit the header on navigations
+/// and some same-origin `GET`s, so its absence says nothing about the caller.
+fn origin_allowed(origin: Option<&str>, dev_origin: Option<&str>) -> bool {
+  let Some(origin) = origin else {
+    return true;
+  };
+  APP_ORIGINS.contains(&origin) || dev_origin == Some(origin)
+}
+
+/// Whether `path` addresses one of the allowed routers.
+///
+/// Dot segments and encoded separators are rejected outright so a path
+/// cannot climb out of an allowed prefix once the backend decodes it.
+fn is_allowed(path: &str) -> bool {
+  let lowered = path.to_ascii_lowercase();
+  if ["%2e", "%2f", "%5c"].iter().any(|encoded| lowered.contains(encoded))
+    || path.split('/').any(|segment| segment == "." || segment == "..")
+  {
+    return false;
+  }
+
+  let Some(route) = path.strip_prefix(API_PREFIX) else {
+    return false;
+  };
+  ALLOWED_ROUTES.iter().any(|allowed| {
+    route
+      .strip_prefix(allowed)
+      .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+  })
+}
+
+async fn forward(app: &AppHandle, request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+  let path = request.uri().path();
+  if !is_allowed(path) {
+    log::warn!("proxy: rejected {} {path}", request.method());
+    return error(StatusCode::FORBIDDEN, "path is not allowed");
+  }
+  let is_auth = path
+    .strip_prefix(API_PREFIX)
+    .and_then(|route| route.strip_prefix(AUTH_ROUTE))
+    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+  let path_and_query = request
+    .uri()
+    .path_and_query()
+    .map_or(path, |p| p.as_str())
+    .to_string();
+
+  let (parts, body) = request.into_parts();
+  let mut upstream = app
+    .state::<BackendEndpoint>()
+    .request(parts.method, &path_and_query)
+    .timeout(PROXY_TIMEOUT);
+  for (name, value) in &parts.headers {
+    if !DROPPED_REQUEST_HEADERS.contains(name) && name != TOKEN_HEADER {
+      upstream = upstream.header(name, value);
+    }
+  }
+
+  let mut upstream = match upstream.body(body).send().await {
+    Ok(response) => response,
+    Err(e) => {
+      log::warn!("proxy: {path_and_query} failed: {e}");
+      return error(StatusCode::BAD_GATEWAY, "the backend is unavailable");
+    }
+  };
+
+  let mut response = Response::builder().status(upstream.status());
+  if let Some(headers) = response.headers_mut() {
+    for (name, value) in upstream.headers() {
+      // The refresh token cookie must not reach the webview
+      if DROPPED_RESPONSE_HEADERS.contains(name) || (is_auth && name == header::SET_COOKIE) {
+        continue;
+      }
+      headers.append(name, value.clone());
+    }
+  }
+
+  // Tauri's scheme responder cannot stream, so the body is read in full,
+  // up to MAX_RESPONSE_BYTES
+  if upstream
+    .content_length()
+    .is_some_and(|length| length > MAX_RESPONSE_BYTES as u64)
+  {
+    log::warn!("proxy: {path_and_query} response is too large");
+    return error(StatusCode::BAD_GATEWAY, "the backend response is too large");
+  }
+  let mut body = Vec::new();
+  loop {
+    match upstream.chunk().await {
+      Ok(Some(chunk)) if body.len() + chunk.len() > MAX_RESPONSE_BYTES => {
+        log::warn!("proxy: {path_and_query} response is too large");
+        return error(StatusCode::BAD_GATEWAY, "the backend response is too large");
+      }
+      Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+      Ok(None) => break,
+      Err(e) => {
+        log::warn!("proxy: reading {path_and_query} failed: {e}");
+        return error(StatusCode::BAD_GATEWAY, "the backend response was interrupted");
+      }
+    }
+  }
+
+  response
+    .body(body)
+    .unwrap_or_else(|_| error(StatusCode::BAD_GATEWAY, "invalid backend response"))
+}
+
+/// Answers CORS preflights locally; the backend has CORS disabled.
+fn preflight(request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+  let requested_headers = request
+    .headers()
+    .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+    .cloned()
+    .unwrap_or_else(|| HeaderValue::from_static("content-type, authorization"));
+
+  Response::builder()
+    .status(StatusCode::NO_CONTENT)
+    .header(
+      header::ACCESS_CONTROL_ALLOW_METHODS,
+      "GET, POST, PUT, PATCH, DELETE, OPTIONS",
+    )
+    .header(header::ACCESS_CONTROL_ALLOW_HEADERS, requested_headers)
+    .header(header::ACCESS_CONTROL_MAX_AGE, "3600")
+    .body(Vec::new())
+    .unwrap()
+}
+
+/// Lets the calling page read the response. Foreign origins were rejected
+/// in [`handle`], so the origin is one of the app's own.
+fn allow_origin(headers: &mut HeaderMap, origin: Option<HeaderValue>) {
+  if let Some(origin) = origin {
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    headers.insert(
+      header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+      HeaderValue::from_static("true"),
+    );
+    headers.append(header::VARY, HeaderValue::from_static("Origin"));
+  }
+}
+
+fn error(status: StatusCode, detail: &str) -> Response<Vec<u8>> {
+  let body = serde_json::json!({ "detail": detail }).to_string();
+  Response::builder()
+    .status(status)
+    .header(header::CONTENT_TYPE, "application/json")
+    .body(body.into_bytes())
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn allows_routes_and_their_subpaths() {
+    assert!(is_allowed("/api/v1/auth"));
+    assert!(is_allowed("/api/v1/auth/login"));
+    assert!(is_allowed("/api/v1/journal/42"));
+    assert!(is_allowed("/api/v1/health"));
+  }
+
+  #[test]
+  fn rejects_routes_outside_the_allowlist() {
+    assert!(!is_allowed("/api/v1/authx"));
+    assert!(!is_allowed("/api/v1/admin"));
+    assert!(!is_allowed("/api/v2/journal"));
+    assert!(!is_allowed("/docs"));
+  }
+
+  #[test]
+  fn rejects_dot_segments() {
+    assert!(!is_allowed("/api/v1/journal/../admin"));
+    assert!(!is_allowed("/api/v1/journal/./x"));
+  }
+
+  #[test]
+  fn rejects_encoded_traversal() {
+    assert!(!is_allowed("/api/v1/journal/%2e%2e/admin"));
+    assert!(!is_allowed("/api/v1/journal/%2E%2E/admin"));
+    assert!(!is_allowed("/api/v1/journal%2f..%2fadmin"));
+    assert!(!is_allowed("/api/v1/journal/..%5cadmin"));
+  }
+
+  #[test]
+  fn allows_app_origins() {
+    for origin in APP_ORIGINS {
+      assert!(origin_allowed(Some(origin), None));
+    }
+  }
+
+  #[test]
+  fn rejects_foreign_origins() {
+    assert!(!origin_allowed(Some("https://evil.example"), None));
+    assert!(!origin_allowed(Some("null"), None));
+    assert!(!origin_allowed(Some(""), None));
+    assert!(!origin_allowed(Some("http://localhost:3000"), None));
+  }
+
+  #[test]
+  fn allows_missing_origin() {
+    assert!(origin_allowed(None, None));
+  }
+
+  #[test]
+  fn allows_dev_origin_only_when_set() {
+    let dev = Some("http://localhost:3000");
+    assert!(origin_allowed(Some("http://localhost:3000"), dev));
+    assert!(!origin_allowed(Some("http://localhost:3001"), dev));
+  }
+}
@@ -0,0 +1,187 @@
+//! Readiness gating for the main window.
+//!
+//! After every sidecar spawn the shell polls the backend's `/health`,
+//! `/startup` and `/ready` probes (see `routers/health.py`). Until the first
+//! `/ready` passes, a small splash window is shown instead of the main window
+//! so the UI never races the Python startup. If the probes time out the splash
+//! switches to an error screen whose retry button calls [`readiness_retry`].
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{App, AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder, WindowEvent};
+
+use crate::app_lock;
+use crate::backend::BackendEndpoint;
+use crate::sidecar;
+use crate::util::lock;
+use crate::window::MAIN_WINDOW;
+
+/// Event emitted to all windows whenever the readiness state changes.
+pub const READINESS_EVENT: &str = "readiness://changed";
+
+pub const SPLASH_WINDOW: &str = "splash";
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Time the backend gets to pass all probes after being spawned.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Startup progress of the backend, in probe order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum Readiness {
+  /// The sidecar is starting and `/health` does not answer yet.
+  Starting,
+  /// `/health` passed; waiting for `/startup`.
+  Alive,
+  /// `/startup` passed; waiting for `/ready`.
+  Started,
+  Ready,
+  Failed { reason: String },
+}
+
+pub struct ReadinessState {
+  current: Mutex<Readiness>,
+  /// Bumped on every new probe run so runs for a previous spawn stop early.
+  generation: AtomicU64,
+}
+
+impl Default for ReadinessState {
+  fn default() -> Self {
+    Self {
+      current: Mutex::new(Readiness::Starting),
+      generation: AtomicU64::new(0),
+    }
+  }
+}
+
+impl ReadinessState {
+  pub fn get(&self) -> Readiness {
+    lock(&self.current).clone()
+  }
+
+  pub fn is_ready(&self) -> bool {
+    *lock(&self.current) == Readiness::Ready
+  }
+
+  fn is_current(&self, generation: u64) -> bool {
+    self.generation.load(Ordering::SeqCst) == generation
+  }
+}
+
+/// Opens the splash window shown while the backend starts.
+///
+/// Closing it before the backend is ready quits the app, since the main
+/// window is still hidden at that point.
+pub fn create_splash_window(app: &App) -> tauri::Result<()> {
+  let splash = WebviewWindowBuilder::new(app, SPLASH_WINDOW, WebviewUrl::App("splash.html".into()))
+    .title("Soul Sense")
+    .inner_size(420.0, 260.0)
+    .resizable(false)
+    .center()
+    .build()?;
+
+  let app = app.handle().clone();
+  splash.on_window_event(move |event| {
+    if let WindowEvent::CloseRequested { .. } = event {
+      if app.state::<ReadinessState>().get() != Readiness::Ready {
+        app.exit(0);
+      }
+    }
+  });
+  Ok(())
+}
+
+/// Starts probing a freshly spawned sidecar, cancelling any earlier run.
+pub fn begin(app: &AppHandle) {
+  let generation = app
+    .state::<ReadinessState>()
+    .generation
+    .fetch_add(1, Ordering::SeqCst)
+    + 1;
+  set(app, Readiness::Starting);
+  let app = app.clone();
+  tauri::async_runtime::spawn(async move { probe(app, generation).await });
+}
+
+/// Marks startup as failed, e.g. when the supervisor gives up.
+pub fn fail(app: &AppHandle, reason: String) {
+  let state = app.state::<ReadinessState>();
+  state.generation.fetch_add(1, Ordering::SeqCst);
+  set(app, Readiness::Failed { reason });
+}
+
+async fn probe(app: AppHandle, generation: u64) {
+  let deadline = Instant::now() + STARTUP_TIMEOUT;
+  let steps = [
+    ("/health", Readiness::Alive),
+    ("/startup", Readiness::Started),
+    ("/ready", Readiness::Ready),
+  ];
+  for (path, reached) in steps {
+    loop {
+      if !app.state::<ReadinessState>().is_current(generation) {
+        return;
+      }
+      if passes(&app, path).await {
+        break;
+      }
+      if Instant::now() >= deadline {
+        let reason = format!("the backend did not pass {path} within {STARTUP_TIMEOUT:?}");
+        log::error!("readiness: {reason}");
+        set(&app, Readiness::Failed { reason });
+        return;
+      }
+      tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    set(&app, reached);
+  }
+
+  log::info!("backend is ready");
+  reveal_main_window(&app);
+}
+
+async fn passes(app: &AppHandle, path: &str) -> bool {
+  let request = app.state::<BackendEndpoint>().get(path);
+  match request.send().await {
+    Ok(response) => response.status().is_success(),
+    Err(_) => false,
+  }
+}
+
+fn set(app: &AppHandle, readiness: Readiness) {
+  *lock(&app.state::<ReadinessState>().current) = readiness.clone();
+  if let Err(e) = app.emit(READINESS_EVENT, readiness) {
+    log::warn!("failed to emit {READINESS_EVENT}: {e}");
+  }
+}
+
+/// Shows the main window, or the lock window if a PIN is set, and closes
+/// the splash, on the first `/ready` only.
+fn reveal_main_window(app: &AppHandle) {
+  let Some(splash) = app.get_webview_window(SPLASH_WINDOW) else {
+    return;
+  };
+  if app_lock::may_show(app, MAIN_WINDOW) {
+    if let Some(main) = app.get_webview_window(MAIN_WINDOW) {
+      let _ = main.show();
+      let _ = main.set_focus();
+    }
+  }
+  let _ = splash.destroy();
+}
+
+/// Returns the current readiness of the backend.
+#[tauri::command]
+pub fn readiness_state(state: tauri::State<'_, ReadinessState>) -> Readiness {
+  state.get()
+}
+
+/// Restarts the backend after a failed startup and probes it again.
+#[tauri::command]
+pub fn readiness_retry(app: AppHandle) {
+  log::info!("readiness: retry requested");
+  set(&app, Readiness::Starting);
+  sidecar::restart(&app);
+}
@@ -0,0 +1,574 @@
+//! Daily check-in reminders.
+//!
+//! Rules live in `settings.json` and are evaluated by a scheduler in the
+//! shell, so reminders keep firing while the main window is hidden to the
+//! tray. Each rule fires at a local time of day on selected weekdays; a
+//! reminder that falls inside quiet hours is held back until they end, and
+//! one missed by more than [`MISSED_GRACE`] (e.g. while the machine slept) is
+//! skipped rather than delivered late.
+//!
+//! Desktop notifications carry no click callback, so the shell does not
+//! navigate on its own: every reminder shown is also emitted to the main
+//! window as [`FIRED_EVENT`] with the route it suggests, and the page decides
+//! whether to offer or open it without interrupting what the user is doing.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, TimeDelta, Weekday};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::notify::{self, Category};
+use crate::settings::SettingsStore;
+use crate::util::lock;
+use crate::window::{EXAM_START_ROUTE, JOURNAL_NEW_ROUTE, MAIN_WINDOW};
+
+/// Event emitted to the main window for every reminder shown.
+pub const FIRED_EVENT: &str = "reminders://fired";
+
+/// How often the scheduler checks for due reminders.
+const POLL_INTERVAL: Duration = Duration::from_secs(20);
+
+/// How late a reminder may still be delivered.
+const MISSED_GRACE: TimeDelta = TimeDelta::minutes(60);
+
+const MAX_SNOOZE_MINUTES: u32 = 12 * 60;
+
+/// Source of the current local time, swapped out in tests.
+pub trait Clock {
+  fn now(&self) -> NaiveDateTime;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> NaiveDateTime {
+    Local::now().naive_local()
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReminderKind {
+  Journal,
+  Exam,
+}
+
+impl ReminderKind {
+  fn route(self) -> &'static str {
+    match self {
+      Self::Journal => JOURNAL_NEW_ROUTE,
+      Self::Exam => EXAM_START_ROUTE,
+    }
+  }
+
+  fn message(self) -> (&'static str, &'static str) {
+    match self {
+      Self::Journal => (
+        "Time to journal",
+        "Take a moment to write down how today went.",
+      ),
+      Self::Exam => (
+        "Time for a check-in",
+        "Retake the EQ check-in to see how you are doing.",
+      ),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderRule {
+  pub id: String,
+  pub kind: ReminderKind,
+  /// Local time of day, e.g. `"20:30"`.
+  pub time: NaiveTime,
+  /// Days the rule fires on; empty means every day.
+  #[serde(default)]
+  pub weekdays: Vec<Weekday>,
+  #[serde(default = "enabled_by_default")]
+  pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+  true
+}
+
+impl ReminderRule {
+  fn fires_on(&self, weekday: Weekday) -> bool {
+    self.weekdays.is_empty() || self.weekdays.contains(&weekday)
+  }
+}
+
+/// Local time range in which reminders are held back; it may span midnight.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+  pub start: NaiveTime,
+  pub end: NaiveTime,
+}
+
+impl QuietHours {
+  fn contains(&self, time: NaiveTime) -> bool {
+    if self.start <= self.end {
+      self.start <= time && time < self.end
+    } else {
+      time >= self.start || time < self.end
+    }
+  }
+
+  /// Moves `at` to the end of quiet hours if it falls inside them.
+  fn defer(&self, at: NaiveDateTime) -> NaiveDateTime {
+    if !self.contains(at.time()) {
+      return at;
+    }
+    let end = at.date().and_time(self.end);
+    if end > at {
+      end
+    } else {
+      end + TimeDelta::days(1)
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ReminderSettings {
+  pub rules: Vec<ReminderRule>,
+  pub snooze_minutes: u32,
+  pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for ReminderSettings {
+  fn default() -> Self {
+    Self {
+      rules: Vec::new(),
+      snooze_minutes: 10,
+      quiet_hours: None,
+    }
+  }
+}
+
+impl ReminderSettings {
+  pub fn validate(&self) -> Result<(), String> {
+    if !(1..=MAX_SNOOZE_MINUTES).contains(&self.snooze_minutes) {
+      return Err(format!(
+        "snooze must be between 1 and {MAX_SNOOZE_MINUTES} minutes"
+      ));
+    }
+    for (i, rule) in self.rules.iter().enumerate() {
+      if rule.id.trim().is_empty() {
+        return Err("reminder ids must not be empty".to_string());
+      }
+      if self.rules[..i].iter().any(|other| other.id == rule.id) {
+        return Err(format!("duplicate reminder id {:?}", rule.id));
+      }
+    }
+    Ok(())
+  }
+
+  fn rule(&self, id: &str) -> Option<&ReminderRule> {
+    self.rules.iter().find(|rule| rule.id == id)
+  }
+
+  /// First time after `after` at which `rule` fires, quiet hours applied.
+  fn next_occurrence(&self, rule: &ReminderRule, after: NaiveDateTime) -> Option<NaiveDateTime> {
+    // Start a day back: yesterday's late reminder may have been deferred
+    // past `after` by quiet hours
+    let first = after.date() - TimeDelta::days(1);
+    (0..=8)
+      .map(|offset| first + TimeDelta::days(offset))
+      .filter(|date| rule.fires_on(date.weekday()))
+      .map(|date| self.defer(date.and_time(rule.time)))
+      .find(|at| *at > after)
+  }
+
+  fn defer(&self, at: NaiveDateTime) -> NaiveDateTime {
+    match self.quiet_hours {
+      Some(quiet) => quiet.defer(at),
+      None => at,
+    }
+  }
+}
+
+/// Tracks when each rule fires next and hands out the ones that are due.
+pub struct Scheduler<C> {
+  clock: C,
+  settings: ReminderSettings,
+  next: HashMap<String, NaiveDateTime>,
+  snoozed: HashMap<String, NaiveDateTime>,
+}
+
+impl<C: Clock> Scheduler<C> {
+  pub fn new(clock: C, settings: ReminderSettings) -> Self {
+    let mut scheduler = Self {
+      clock,
+      settings,
+      next: HashMap::new(),
+      snoozed: HashMap::new(),
+    };
+    scheduler.reschedule();
+    scheduler
+  }
+
+  pub fn settings(&self) -> &ReminderSettings {
+    &self.settings
+  }
+
+  /// Replaces the rules; pending snoozes survive for rules that still exist
+  /// and are enabled.
+  pub fn set_settings(&mut self, settings: ReminderSettings) {
+    self.settings = settings;
+    let settings = &self.settings;
+    self
+      .snoozed
+      .retain(|id, _| settings.rule(id).is_some_and(|rule| rule.enabled));
+    self.reschedule();
+  }
+
+  fn reschedule(&mut self) {
+    let now = self.clock.now();
+    self.next = self
+      .settings
+      .rules
+      .iter()
+      .filter(|rule| rule.enabled)
+      .filter_map(|rule| {
+        let at = self.settings.next_occurrence(rule, now)?;
+        Some((rule.id.clone(), at))
+      })
+      .collect();
+  }
+
+  /// When the reminder `id` fires next, snoozes included.
+  #[cfg(test)]
+  fn next_fire(&self, id: &str) -> Option<NaiveDateTime> {
+    match (self.next.get(id), self.snoozed.get(id)) {
+      (Some(next), Some(snoozed)) => Some(*next.min(snoozed)),
+      (next, snoozed) => next.or(snoozed).copied(),
+    }
+  }
+
+  /// Fires the reminder `id` again after the configured snooze.
+  pub fn snooze(&mut self, id: &str) -> Result<NaiveDateTime, String> {
+    match self.settings.rule(id) {
+      None => return Err(format!("unknown reminder {id:?}")),
+      Some(rule) if !rule.enabled => return Err(format!("reminder {id:?} is disabled")),
+      Some(_) => {}
+    }
+    let delay = TimeDelta::minutes(self.settings.snooze_minutes.into());
+    let at = self.settings.defer(self.clock.now() + delay);
+    self.snoozed.insert(id.to_string(), at);
+    Ok(at)
+  }
+
+  /// Returns the rules that became due since the last call.
+  pub fn due(&mut self) -> Vec<ReminderRule> {
+    let now = self.clock.now();
+    let mut due = Vec::new();
+
+    let snoozed: Vec<_> = self
+      .snoozed
+      .iter()
+      .filter(|(_, at)| **at <= now)
+      .map(|(id, at)| (id.clone(), *at))
+      .collect();
+    for (id, at) in snoozed {
+      self.snoozed.remove(&id);
+      let rule = self.settings.rule(&id).filter(|rule| rule.enabled);
+      if now - at <= MISSED_GRACE {
+        due.extend(rule.cloned());
+      }
+    }
+
+    let scheduled: Vec<_> = self
+      .next
+      .iter()
+      .filter(|(_, at)| **at <= now)
+      .map(|(id, at)| (id.clone(), *at))
+      .collect();
+    for (id, at) in scheduled {
+      let Some(rule) = self.settings.rule(&id).cloned() else {
+        continue;
+      };
+      match self.settings.next_occurrence(&rule, now) {
+        Some(next) => self.next.insert(id.clone(), next),
+        None => self.next.remove(&id),
+      };
+      if now - at > MISSED_GRACE {
+        log::info!(
+          "reminders: skipped {id}, missed by {} min",
+          (now - at).num_minutes()
+        );
+      } else if !due.iter().any(|fired| fired.id == id) {
+        due.push(rule);
+      }
+    }
+    due
+  }
+}
+
+pub struct Reminders {
+  scheduler: Mutex<Scheduler<SystemClock>>,
+}
+
+impl Reminders {
+  pub fn new(settings: ReminderSettings) -> Self {
+    Self {
+      scheduler: Mutex::new(Scheduler::new(SystemClock, settings)),
+    }
+  }
+}
+
+/// Payload of [`FIRED_EVENT`].
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Fired<'a> {
+  id: &'a str,
+  kind: ReminderKind,
+  route: &'static str,
+}
+
+/// Starts the scheduler.
+pub fn start(app: &AppHandle) {
+  let app = app.clone();
+  tauri::async_runtime::spawn(async move {
+    loop {
+      let due = lock(&app.state::<Reminders>().scheduler).due();
+      for rule in due {
+        show(&app, &rule);
+      }
+      tokio::time::sleep(POLL_INTERVAL).await;
+    }
+  });
+}
+
+fn show(app: &AppHandle, rule: &ReminderRule) {
+  let (title, body) = rule.kind.message();
+  log::info!("reminders: firing {}", rule.id);
+  if !notify::show(app, Category::Reminder, title, body) {
+    return;
+  }
+  let fired = Fired {
+    id: &rule.id,
+    kind: rule.kind,
+    route: rule.kind.route(),
+  };
+  if let Err(e) = app.emit_to(MAIN_WINDOW, FIRED_EVENT, fired) {
+    log::warn!("failed to emit {FIRED_EVENT}: {e}");
+  }
+}
+
+/// Returns the reminder rules, snooze length and quiet hours.
+#[tauri::command]
+pub fn reminder_settings(reminders: tauri::State<'_, Reminders>) -> ReminderSettings {
+  lock(&reminders.scheduler).settings().clone()
+}
+
+/// Validates, persists and applies new reminder settings.
+#[tauri::command]
+pub fn set_reminder_settings(
+  settings: tauri::State<'_, SettingsStore>,
+  reminders: tauri::State<'_, Reminders>,
+  value: ReminderSettings,
+) -> Result<(), String> {
+  value.validate()?;
+  settings.update(|settings| settings.reminders = value.clone())?;
+  lock(&reminders.scheduler).set_settings(value);
+  Ok(())
+}
+
+/// Fires the reminder `id` again after the configured snooze.
+#[tauri::command]
+pub fn snooze_reminder(reminders: tauri::State<'_, Reminders>, id: String) -> Result<(), String> {
+  lock(&reminders.scheduler).snooze(&id).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::cell::Cell;
+  use std::rc::Rc;
+
+  use chrono::NaiveDate;
+
+  use super::*;
+
+  /// Clock the tests move by hand.
+  #[derive(Clone)]
+  struct MockClock(Rc<Cell<NaiveDateTime>>);
+
+  impl MockClock {
+    fn at(date: NaiveDate, time: &str) -> Self {
+      Self(Rc::new(Cell::new(date.and_time(time.parse().unwrap()))))
+    }
+
+    fn set(&self, date: NaiveDate, time: &str) {
+      self.0.set(date.and_time(time.parse().unwrap()));
+    }
+
+    fn advance(&self, minutes: i64) {
+      self.0.set(self.0.get() + TimeDelta::minutes(minutes));
+    }
+  }
+
+  impl Clock for MockClock {
+    fn now(&self) -> NaiveDateTime {
+      self.0.get()
+    }
+  }
+
+  // 2024-06-03 is a Monday
+  fn monday() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 6, 3).unwrap()
+  }
+
+  fn rule(id: &str, time: &str, weekdays: &[Weekday]) -> ReminderRule {
+    ReminderRule {
+      id: id.to_string(),
+      kind: ReminderKind::Journal,
+      time: time.parse().unwrap(),
+      weekdays: weekdays.to_vec(),
+      enabled: true,
+    }
+  }
+
+  fn settings(rules: Vec<ReminderRule>) -> ReminderSettings {
+    ReminderSettings {
+      rules,
+      ..Default::default()
+    }
+  }
+
+  fn ids(due: Vec<ReminderRule>) -> Vec<String> {
+    due.into_iter().map(|rule| rule.id).collect()
+  }
+
+  #[test]
+  fn fires_once_at_the_rule_time() {
+    let clock = MockClock::at(monday(), "20:00");
+    let mut scheduler =
+      Scheduler::new(clock.clone(), settings(vec![rule("evening", "20:30", &[])]));
+
+    clock.advance(29);
+    assert!(scheduler.due().is_empty());
+    clock.advance(1);
+    assert_eq!(ids(scheduler.due()), ["evening"]);
+    clock.advance(1);
+    assert!(scheduler.due().is_empty());
+
+    clock.set(monday().succ_opt().unwrap(), "20:30");
+    assert_eq!(ids(scheduler.due()), ["evening"]);
+  }
+
+  #[test]
+  fn only_fires_on_selected_weekdays() {
+    let clock = MockClock::at(monday(), "08:00");
+    let rules = vec![rule("weekly", "09:00", &[Weekday::Wed])];
+    let mut scheduler = Scheduler::new(clock.clone(), settings(rules));
+
+    assert_eq!(
+      scheduler.next_fire("weekly"),
+      Some(
+        NaiveDate::from_ymd_opt(2024, 6, 5)
+          .unwrap()
+          .and_hms_opt(9, 0, 0)
+          .unwrap()
+      )
+    );
+    clock.advance(60);
+    assert!(scheduler.due().is_empty());
+  }
+
+  #[test]
+  fn disabled_rules_never_fire() {
+    let clock = MockClock::at(monday(), "08:00");
+    let mut disabled = rule("off", "08:30", &[]);
+    disabled.enabled = false;
+    let mut scheduler = Scheduler::new(clock.clone(), settings(vec![disabled]));
+
+    clock.advance(30);
+    assert!(scheduler.due().is_empty());
+    assert_eq!(scheduler.next_fire("off"), None);
+  }
+
+  #[test]
+  fn quiet_hours_defer_until_they_end() {
+    let clock = MockClock::at(monday(), "22:00");
+    let mut reminders = settings(vec![rule("late", "23:00", &[])]);
+    reminders.quiet_hours = Some(QuietHours {
+      start: "22:30".parse().unwrap(),
+      end: "07:00".parse().unwrap(),
+    });
+    let mut scheduler = Scheduler::new(clock.clone(), reminders);
+
+    clock.advance(60);
+    assert!(scheduler.due().is_empty());
+
+    clock.set(monday().succ_opt().unwrap(), "07:00");
+    assert_eq!(ids(scheduler.due()), ["late"]);
+  }
+
+  #[test]
+  fn snoozed_reminders_fire_again() {
+    let clock = MockClock::at(monday(), "08:59");
+    let mut scheduler =
+      Scheduler::new(clock.clone(), settings(vec![rule("morning", "09:00", &[])]));
+    clock.advance(1);
+    assert_eq!(ids(scheduler.due()), ["morning"]);
+
+    scheduler.snooze("morning").unwrap();
+    clock.advance(9);
+    assert!(scheduler.due().is_empty());
+    clock.advance(1);
+    assert_eq!(ids(scheduler.due()), ["morning"]);
+    assert!(scheduler.snooze("missing").is_err());
+  }
+
+  #[test]
+  fn disabled_rules_cannot_be_snoozed() {
+    let clock = MockClock::at(monday(), "08:59");
+    let mut reminders = settings(vec![rule("morning", "09:00", &[])]);
+    let mut scheduler = Scheduler::new(clock.clone(), reminders.clone());
+    clock.advance(1);
+    assert_eq!(ids(scheduler.due()), ["morning"]);
+    scheduler.snooze("morning").unwrap();
+
+    // Disabling the rule drops the pending snooze
+    reminders.rules[0].enabled = false;
+    scheduler.set_settings(reminders);
+    clock.advance(10);
+    assert!(scheduler.due().is_empty());
+    assert_eq!(scheduler.next_fire("morning"), None);
+    assert!(scheduler.snooze("morning").is_err());
+  }
+
+  #[test]
+  fn long_missed_reminders_are_skipped() {
+    let clock = MockClock::at(monday(), "08:00");
+    let mut scheduler =
+      Scheduler::new(clock.clone(), settings(vec![rule("morning", "08:30", &[])]));
+
+    // The machine sleeps through the reminder
+    clock.set(monday(), "11:00");
+    assert!(scheduler.due().is_empty());
+
+    clock.set(monday().succ_opt().unwrap(), "08:45");
+    assert_eq!(ids(scheduler.due()), ["morning"]);
+  }
+
+  #[test]
+  fn validation_rejects_bad_settings() {
+    let duplicated = settings(vec![rule("a", "08:00", &[]), rule("a", "09:00", &[])]);
+    assert!(duplicated.validate().is_err());
+
+    let mut no_snooze = settings(vec![rule("a", "08:00", &[])]);
+    no_snooze.snooze_minutes = 0;
+    assert!(no_snooze.validate().is_err());
+
+    assert!(settings(vec![rule("a", "08:00", &[Weekday::Mon])])
+      .validate()
+      .is_ok());
+  }
+}
@@ -0,0 +1,69 @@
+//! Shell preferences persisted as `settings.json` in the app config dir.
+//!
+//! Unknown or missing fields fall back to their defaults, so older files keep
+//! loading as new preferences are added.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::paths;
+use crate::reminders::ReminderSettings;
+use crate::util::lock;
+
+const FILE_NAME: &str = "settings.json";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+  /// Log level chosen at runtime; `SOULSENSE_LOG_LEVEL` takes precedence.
+  pub log_level: Option<String>,
+  /// Hide the main window to the tray on close instead of quitting, so the
+  /// backend and reminders keep running.
+  pub close_to_tray: bool,
+  /// Daily check-in reminder rules.
+  pub reminders: ReminderSettings,
+  /// Silence all OS notifications, reminders included.
+  pub do_not_disturb: bool,
+}
+
+pub struct SettingsStore {
+  path: PathBuf,
+  current: Mutex<Settings>,
+}
+
+impl SettingsStore {
+  /// Loads the settings file, starting from defaults if it is missing or
+  /// unreadable.
+  pub fn load(app: &AppHandle) -> tauri::Result<Self> {
+    let path = app.path().app_config_dir()?.join(FILE_NAME);
+    let current = match fs::read_to_string(&path) {
+      Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+        log::warn!("ignoring invalid {}: {e}", path.display());
+        Settings::default()
+      }),
+      Err(_) => Settings::default(),
+    };
+    Ok(Self {
+      path,
+      current: Mutex::new(current),
+    })
+  }
+
+  pub fn get(&self) -> Settings {
+    lock(&self.current).clone()
+  }
+
+  /// Applies `change` and writes the result back to disk.
+  pub fn update(&self, change: impl FnOnce(&mut Settings)) -> Result<Settings, String> {
+    let mut current = lock(&self.current);
+    change(&mut current);
+
+    let contents = serde_json::to_string_pretty(&*current).map_err(|e| e.to_string())?;
+    paths::write_private(&self.path, contents.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(current.clone())
+  }
+}
@@ -0,0 +1,481 @@
+//! Supervision of the bundled `soul-sense-backend` sidecar.
+//!
+//! The supervisor owns the spawned [`CommandChild`], watches the command's
+//! event stream for [`CommandEvent::Terminated`] and restarts the backend with
+//! exponential backoff. If the backend keeps crashing it gives up and reports
+//! [`SidecarStatus::Failed`]. Failures to launch at all, port conflicts, and
+//! exits before the first launch became ready, are not retried automatically;
+//! they are reported through [`error::report`] and the user can retry from the
+//! error screen.
+//! Every transition is emitted to the webview as a [`STATUS_EVENT`] so the
+//! frontend can show "reconnecting" instead of failing API calls. Output of
+//! the process is handed to [`sidecar_logs`].
+//!
+//! [`shutdown`] stops the backend when the app exits so uvicorn never outlives
+//! the shell and keeps its port bound.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+
+use crate::backend::BackendEndpoint;
+use crate::database::{self, Database};
+use crate::error::{self, SidecarError};
+use crate::paths::AppDirs;
+use crate::readiness::{self, ReadinessState};
+use crate::sidecar_logs::{self, LineRecorder, SidecarLogs, Stream};
+use crate::util::lock;
+
+/// Name of the bundled backend binary, as listed in `bundle.externalBin`.
+pub(crate) const SIDECAR_NAME: &str = "soul-sense-backend";
+
+/// Event emitted to the webview whenever the sidecar status changes.
+pub const STATUS_EVENT: &str = "sidecar://status";
+
+/// Delay before the first restart; doubled on every consecutive crash.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Crashes within [`CRASH_WINDOW`] after which the supervisor gives up.
+const MAX_CRASHES: usize = 5;
+const CRASH_WINDOW: Duration = Duration::from_secs(120);
+/// A run lasting at least this long is considered healthy and resets the backoff.
+const STABLE_UPTIME: Duration = Duration::from_secs(30);
+/// Time the backend gets to exit after the graceful signal before it is killed.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lifecycle of the backend process as seen by the frontend.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum SidecarStatus {
+  Starting { attempt: u32 },
+  Running { pid: u32 },
+  Reconnecting {
+    attempt: u32,
+    delay_ms: u64,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+  },
+  Failed { reason: String },
+  Stopped,
+}
+
+/// Managed state shared between the supervisor task and invoke commands.
+pub struct SidecarState {
+  child: Mutex<Option<CommandChild>>,
+  status: Mutex<SidecarStatus>,
+  /// Signalled whenever `status` changes, so [`shutdown`] can wait for exit.
+  status_changed: Condvar,
+  supervising: AtomicBool,
+  /// Set by [`restart`] so the supervisor respawns without treating the exit
+  /// as a failure.
+  restart_requested: AtomicBool,
+  shutting_down: AtomicBool,
+}
+
+impl Default for SidecarState {
+  fn default() -> Self {
+    Self {
+      child: Mutex::new(None),
+      status: Mutex::new(SidecarStatus::Stopped),
+      status_changed: Condvar::new(),
+      supervising: AtomicBool::new(false),
+      restart_requested: AtomicBool::new(false),
+      shutting_down: AtomicBool::new(false),
+    }
+  }
+}
+
+impl SidecarState {
+  pub fn status(&self) -> SidecarStatus {
+    lock(&self.status).clone()
+  }
+
+  fn set_child(&self, child: Option<CommandChild>) {
+    *lock(&self.child) = child;
+  }
+
+  fn is_shutting_down(&self) -> bool {
+    self.shutting_down.load(Ordering::SeqCst)
+  }
+}
+
+/// Tracks recent crashes to compute restart delays and detect crash loops.
+#[derive(Default)]
+struct RestartPolicy {
+  crashes: VecDeque<Instant>,
+  consecutive: u32,
+}
+
+impl RestartPolicy {
+  /// Records a crash of a run that lasted `uptime` and returns how long to
+  /// wait before restarting, or `None` if the backend is crash-looping.
+  fn on_crash(&mut self, now: Instant, uptime: Duration) -> Option<Duration> {
+    if uptime >= STABLE_UPTIME {
+      self.consecutive = 0;
+    }
+
+    self.crashes.push_back(now);
+    while let Some(&oldest) = self.crashes.front() {
+      if now.duration_since(oldest) <= CRASH_WINDOW {
+        break;
+      }
+      self.crashes.pop_front();
+    }
+    if self.crashes.len() >= MAX_CRASHES {
+      return None;
+    }
+
+    let delay = INITIAL_BACKOFF
+      .saturating_mul(2u32.saturating_pow(self.consecutive))
+      .min(MAX_BACKOFF);
+    self.consecutive += 1;
+    Some(delay)
+  }
+}
+
+/// Starts the supervisor task for the backend sidecar, unless one is running.
+pub fn start(app: &AppHandle) {
+  if app.state::<SidecarState>().supervising.swap(true, Ordering::SeqCst) {
+    return;
+  }
+  let app = app.clone();
+  tauri::async_runtime::spawn(async move {
+    supervise(&app).await;
+    app.state::<SidecarState>().supervising.store(false, Ordering::SeqCst);
+  });
+}
+
+/// Restarts the backend: a running process is killed so the supervisor
+/// respawns it, and a supervisor that gave up is started again.
+pub fn restart(app: &AppHandle) {
+  let state = app.state::<SidecarState>();
+  if state.is_shutting_down() {
+    return;
+  }
+  if state.supervising.load(Ordering::SeqCst) {
+    let child = lock(&state.child).take();
+    if child.is_some() {
+      state.restart_requested.store(true, Ordering::SeqCst);
+      kill(child);
+    }
+  } else {
+    start(app);
+  }
+}
+
+async fn supervise(app: &AppHandle) {
+  let mut policy = RestartPolicy::default();
+  let mut attempt = 0;
+
+  loop {
+    if app.state::<SidecarState>().is_shutting_down() {
+      set_status(app, SidecarStatus::Stopped);
+      return;
+    }
+
+    attempt += 1;
+    // Later launches are respawns of a backend that has started before
+    let first_launch = attempt == 1;
+    set_status(app, SidecarStatus::Starting { attempt });
+
+    if let Err(e) = database::prepare(app).await {
+      return give_up(app, SidecarError::Database(e));
+    }
+
+    let started_at = Instant::now();
+    let endpoint = app.state::<BackendEndpoint>();
+    let spawned = endpoint.ensure_available().and_then(|()| {
+      app
+        .shell()
+        .sidecar(SIDECAR_NAME)
+        .and_then(|command| {
+          command
+            .args(endpoint.sidecar_args())
+            .envs(endpoint.sidecar_env())
+            .envs(app.state::<AppDirs>().sidecar_env(app.state::<Database>().url()))
+            .envs(app.state::<Database>().sidecar_env())
+            // Flush Python's stdout per line so it reaches the log pipeline
+            .env("PYTHONUNBUFFERED", "1")
+            .spawn()
+        })
+        .map_err(SidecarError::from)
+    });
+    let (mut rx, child) = match spawned {
+      Ok(spawned) => spawned,
+      Err(e) => return give_up(app, e),
+    };
+
+    let pid = child.pid();
+    let state = app.state::<SidecarState>();
+    state.set_child(Some(child));
+    set_status(app, SidecarStatus::Running { pid });
+    log::info!("sidecar started with pid {pid}");
+    readiness::begin(app);
+
+    // `shutdown` may have run while the process was being spawned.
+    if state.is_shutting_down() {
+      kill(lock(&state.child).take());
+    }
+
+    let logs = app.state::<SidecarLogs>();
+    let mut recorder = LineRecorder::default();
+    let mut port_conflict = false;
+    let mut terminated = None;
+    while let Some(event) = rx.recv().await {
+      match event {
+        CommandEvent::Stdout(line) => recorder.record(&logs, Stream::Stdout, &line),
+        CommandEvent::Stderr(line) => {
+          port_conflict |= error::is_port_conflict(&line);
+          recorder.record(&logs, Stream::Stderr, &line);
+        }
+        CommandEvent::Error(e) => {
+          log::warn!(target: sidecar_logs::LOG_TARGET, "failed to read output: {e}")
+        }
+        CommandEvent::Terminated(payload) => {
+          terminated = Some(payload);
+          break;
+        }
+        _ => {}
+      }
+    }
+    state.set_child(None);
+
+    let (exit_code, signal) = terminated.map_or((None, None), |t| (t.code, t.signal));
+    if state.is_shutting_down() {
+      log::info!("sidecar stopped (code: {exit_code:?}, signal: {signal:?})");
+      set_status(app, SidecarStatus::Stopped);
+      return;
+    }
+    if state.restart_requested.swap(false, Ordering::SeqCst) {
+      log::info!("restarting sidecar on request");
+      continue;
+    }
+    if !app.state::<ReadinessState>().is_ready() {
+      if let Some(port) = endpoint.port().filter(|_| port_conflict) {
+        return give_up(app, SidecarError::PortConflict { port });
+      }
+      if first_launch {
+        return give_up(
+          app,
+          SidecarError::EarlyExit {
+            code: exit_code,
+            signal,
+          },
+        );
+      }
+    }
+    log::warn!("sidecar exited (code: {exit_code:?}, signal: {signal:?})");
+
+    let Some(delay) = policy.on_crash(Instant::now(), started_at.elapsed()) else {
+      let reason = format!("backend crashed {MAX_CRASHES} times within {CRASH_WINDOW:?}");
+      log::error!("giving up on sidecar: {reason}");
+      set_status(app, SidecarStatus::Failed { reason: reason.clone() });
+      readiness::fail(app, reason);
+      return;
+    };
+
+    set_status(
+      app,
+      SidecarStatus::Reconnecting {
+        attempt,
+        delay_ms: delay.as_millis() as u64,
+        exit_code,
+        signal,
+      },
+    );
+    tokio::time::sleep(delay).await;
+  }
+}
+
+/// Stops supervising after a startup failure and reports it.
+fn give_up(app: &AppHandle, error: SidecarError) {
+  error::report(app, &error);
+  set_status(
+    app,
+    SidecarStatus::Failed {
+      reason: error.to_string(),
+    },
+  );
+  readiness::fail(app, error.to_string());
+}
+
+fn set_status(app: &AppHandle, status: SidecarStatus) {
+  let state = app.state::<SidecarState>();
+  *lock(&state.status) = status.clone();
+  state.status_changed.notify_all();
+  if let Err(e) = app.emit(STATUS_EVENT, status) {
+    log::warn!("failed to emit {STATUS_EVENT}: {e}");
+  }
+}
+
+/// Returns the current backend status, for frontends that missed earlier events.
+#[tauri::command]
+pub fn sidecar_status(state: tauri::State<'_, SidecarState>) -> SidecarStatus {
+  state.status()
+}
+
+/// Stops the supervisor and terminates the backend.
+///
+/// The process is first asked to exit gracefully (`SIGTERM` on Unix) and is
+/// killed if it is still running after [`SHUTDOWN_TIMEOUT`]. Safe to call more
+/// than once; later calls return immediately.
+pub fn shutdown(app: &AppHandle) {
+  let state = app.state::<SidecarState>();
+  if state.shutting_down.swap(true, Ordering::SeqCst) {
+    return;
+  }
+
+  if let Some(child) = lock(&state.child).take() {
+    stop(&state, child);
+  }
+  app.state::<BackendEndpoint>().release();
+}
+
+/// Signals `child` and waits for the supervisor to see it exit.
+fn stop(state: &SidecarState, child: CommandChild) {
+  log::info!("stopping sidecar (pid {})", child.pid());
+
+  if !terminate(&child) {
+    kill(Some(child));
+    return;
+  }
+
+  let status = lock(&state.status);
+  let (_status, wait) = state
+    .status_changed
+    .wait_timeout_while(status, SHUTDOWN_TIMEOUT, |status| {
+      matches!(status, SidecarStatus::Running { .. })
+    })
+    .unwrap_or_else(PoisonError::into_inner);
+  if wait.timed_out() {
+    log::warn!("sidecar did not exit within {SHUTDOWN_TIMEOUT:?}, killing it");
+    kill(Some(child));
+  }
+}
+
+/// Kills the backend from the panic hook when the main thread panics.
+///
+/// Panics on other threads (e.g. inside an async task) do not bring the app
+/// down, so the backend is left running for those. Locks are only tried here
+/// because the panicking thread may already hold them.
+pub fn install_panic_hook(app: &AppHandle) {
+  let app = app.clone();
+  let previous = std::panic::take_hook();
+  std::panic::set_hook(Box::new(move |info| {
+    previous(info);
+    if std::thread::current().name() != Some("main") {
+      return;
+    }
+    if let Some(state) = app.try_state::<SidecarState>() {
+      state.shutting_down.store(true, Ordering::SeqCst);
+      if let Ok(mut child) = state.child.try_lock() {
+        kill(child.take());
+      }
+    }
+  }));
+}
+
+/// Sends a graceful termination signal, returning `false` if that is not
+/// supported on this platform or failed.
+#[cfg(unix)]
+fn terminate(child: &CommandChild) -> bool {
+  let Ok(pid) = libc::pid_t::try_from(child.pid()) else {
+    return false;
+  };
+  // SAFETY: `kill` has no memory-safety preconditions; the pid belongs to a
+  // child we still own, so it cannot have been reused yet.
+  unsafe { libc::kill(pid, libc::SIGTERM) == 0 }
+}
+
+#[cfg(not(unix))]
+fn terminate(_child: &CommandChild) -> bool {
+  false
+}
+
+fn kill(child: Option<CommandChild>) {
+  if let Some(child) = child {
+    if let Err(e) = child.kill() {
+      log::warn!("failed to kill sidecar: {e}");
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SHORT_RUN: Duration = Duration::from_secs(1);
+
+  #[test]
+  fn backoff_doubles_per_consecutive_crash() {
+    let mut policy = RestartPolicy::default();
+    let start = Instant::now();
+
+    let delays: Vec<_> = (0..4)
+      .map(|i| policy.on_crash(start + Duration::from_secs(i), SHORT_RUN))
+      .collect();
+    assert_eq!(
+      delays,
+      [500, 1000, 2000, 4000].map(|ms| Some(Duration::from_millis(ms)))
+    );
+  }
+
+  #[test]
+  fn gives_up_after_max_crashes_within_the_window() {
+    let mut policy = RestartPolicy::default();
+    let start = Instant::now();
+
+    for i in 0..MAX_CRASHES as u64 - 1 {
+      assert!(policy
+        .on_crash(start + Duration::from_secs(i), SHORT_RUN)
+        .is_some());
+    }
+    assert_eq!(
+      policy.on_crash(start + Duration::from_secs(10), SHORT_RUN),
+      None
+    );
+  }
+
+  #[test]
+  fn crashes_outside_the_window_are_forgotten() {
+    let mut policy = RestartPolicy::default();
+    let mut now = Instant::now();
+
+    for _ in 0..MAX_CRASHES * 2 {
+      assert!(policy.on_crash(now, SHORT_RUN).is_some());
+      now += CRASH_WINDOW + Duration::from_secs(1);
+    }
+  }
+
+  #[test]
+  fn backoff_is_capped() {
+    let mut policy = RestartPolicy::default();
+    let mut now = Instant::now();
+
+    let mut delay = None;
+    for _ in 0..20 {
+      delay = policy.on_crash(now, SHORT_RUN);
+      now += CRASH_WINDOW + Duration::from_secs(1);
+    }
+    assert_eq!(delay, Some(MAX_BACKOFF));
+  }
+
+  #[test]
+  fn a_stable_run_resets_the_backoff() {
+    let mut policy = RestartPolicy::default();
+    let start = Instant::now();
+
+    policy.on_crash(start, SHORT_RUN);
+    policy.on_crash(start + Duration::from_secs(1), SHORT_RUN);
+    assert_eq!(
+      policy.on_crash(start + Duration::from_secs(60), STABLE_UPTIME),
+      Some(INITIAL_BACKOFF)
+    );
+  }
+}
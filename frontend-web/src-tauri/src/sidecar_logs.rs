@@ -0,0 +1,134 @@
+//! Capture of the sidecar's stdout and stderr.
+//!
+//! Every line the backend prints is forwarded to the `log` pipeline under the
+//! `sidecar` target, at the level uvicorn/Python logging put in front of it,
+//! and kept in a bounded ring buffer that backs the frontend's "backend logs"
+//! diagnostics panel.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::util::lock;
+
+/// `log` target used for all backend output.
+pub const LOG_TARGET: &str = "sidecar";
+
+/// Number of lines kept for the diagnostics panel.
+const CAPACITY: usize = 1000;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Stream {
+  Stdout,
+  Stderr,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLine {
+  /// Milliseconds since the Unix epoch at which the shell received the line.
+  pub timestamp_ms: u64,
+  pub stream: Stream,
+  pub level: String,
+  pub message: String,
+}
+
+#[derive(Default)]
+pub struct SidecarLogs {
+  lines: Mutex<VecDeque<LogLine>>,
+}
+
+impl SidecarLogs {
+  fn push(&self, line: LogLine) {
+    let mut lines = lock(&self.lines);
+    if lines.len() == CAPACITY {
+      lines.pop_front();
+    }
+    lines.push_back(line);
+  }
+
+  /// Returns the most recent `limit` lines, oldest first.
+  pub fn tail(&self, limit: usize) -> Vec<LogLine> {
+    let lines = lock(&self.lines);
+    let skip = lines.len().saturating_sub(limit);
+    lines.iter().skip(skip).cloned().collect()
+  }
+}
+
+/// Turns raw output lines of one sidecar run into log records.
+///
+/// Lines without a level prefix, such as traceback frames, inherit the level
+/// of the previous line on the same stream.
+pub struct LineRecorder {
+  stdout_level: log::Level,
+  stderr_level: log::Level,
+}
+
+impl Default for LineRecorder {
+  fn default() -> Self {
+    Self {
+      stdout_level: log::Level::Info,
+      stderr_level: log::Level::Info,
+    }
+  }
+}
+
+impl LineRecorder {
+  pub fn record(&mut self, logs: &SidecarLogs, stream: Stream, bytes: &[u8]) {
+    let text = String::from_utf8_lossy(bytes);
+    let text = text.trim_end();
+    if text.is_empty() {
+      return;
+    }
+
+    let previous = match stream {
+      Stream::Stdout => &mut self.stdout_level,
+      Stream::Stderr => &mut self.stderr_level,
+    };
+    let (level, message) = match parse_level(text) {
+      Some((level, message)) => {
+        *previous = level;
+        (level, message)
+      }
+      None => (*previous, text),
+    };
+
+    log::log!(target: LOG_TARGET, level, "{message}");
+    logs.push(LogLine {
+      timestamp_ms: now_ms(),
+      stream,
+      level: level.as_str().to_lowercase(),
+      message: message.to_string(),
+    });
+  }
+}
+
+/// Splits a uvicorn (`INFO:     message`) or Python logging
+/// (`WARNING:module:message`) prefix off a line.
+fn parse_level(line: &str) -> Option<(log::Level, &str)> {
+  let (prefix, rest) = line.split_once(':')?;
+  let level = match prefix {
+    "CRITICAL" | "ERROR" => log::Level::Error,
+    "WARNING" | "WARN" => log::Level::Warn,
+    "INFO" => log::Level::Info,
+    "DEBUG" => log::Level::Debug,
+    "TRACE" => log::Level::Trace,
+    _ => return None,
+  };
+  Some((level, rest.trim_start()))
+}
+
+fn now_ms() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Returns up to `limit` (default: all buffered) recent backend log lines.
+#[tauri::command]
+pub fn sidecar_logs(logs: tauri::State<'_, SidecarLogs>, limit: Option<usize>) -> Vec<LogLine> {
+  logs.tail(limit.unwrap_or(CAPACITY))
+}
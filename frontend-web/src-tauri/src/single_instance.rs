@@ -0,0 +1,277 @@
+//! Single-instance enforcement with argument forwarding.
+//!
+//! The first launch takes an exclusive lock on `instance.lock` and listens on
+//! a local socket next to it (a Unix domain socket, or a loopback port
+//! recorded in `instance.port` on Windows). Later launches fail to take the
+//! lock, send their command-line arguments (including any deep-link URL) to
+//! that socket and exit. The primary instance focuses its window and emits
+//! the arguments as [`ARGS_EVENT`].
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{App, AppHandle, Emitter};
+
+use crate::{deep_link, paths, window};
+
+/// Event emitted when another launch forwards its arguments.
+pub const ARGS_EVENT: &str = "single-instance://args";
+
+const LOCK_FILE: &str = "instance.lock";
+/// Upper bound on a forwarded message, far above any real command line.
+const MAX_MESSAGE: u64 = 64 * 1024;
+/// How long a secondary launch waits for the primary to start listening.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long the primary waits for a connected launch to send its message,
+/// so a client that never writes cannot stall later launches.
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ForwardedArgs {
+  pub args: Vec<String>,
+}
+
+pub enum Instance {
+  /// This process holds the lock and should keep running.
+  Primary(Server),
+  /// Another process holds the lock and has received our arguments.
+  Secondary,
+}
+
+/// Receiving end owned by the primary instance. The lock is held for as long
+/// as this value (or the thread started by [`Server::listen`]) lives.
+pub struct Server {
+  _lock: File,
+  listener: sys::Listener,
+}
+
+impl Server {
+  /// Calls `on_args` with the arguments of every later launch.
+  pub fn listen(self, on_args: impl Fn(Vec<String>) + Send + 'static) {
+    thread::spawn(move || {
+      let _lock = self._lock;
+      for stream in self.listener.incoming() {
+        let message = stream.and_then(|stream| {
+          stream.set_read_timeout(Some(READ_TIMEOUT))?;
+          let mut message = Vec::new();
+          stream.take(MAX_MESSAGE).read_to_end(&mut message)?;
+          Ok(message)
+        });
+        match message.map(|m| serde_json::from_slice::<Vec<String>>(&m)) {
+          Ok(Ok(args)) => on_args(args),
+          Ok(Err(e)) => log::warn!("ignoring malformed message from another instance: {e}"),
+          Err(e) => log::warn!("failed to read from another instance: {e}"),
+        }
+      }
+    });
+  }
+}
+
+/// Becomes the primary instance for `dir`, or forwards `args` to the
+/// existing one.
+pub fn acquire(dir: &Path, args: &[String]) -> io::Result<Instance> {
+  fs::create_dir_all(dir)?;
+
+  if let Some(lock) = sys::try_lock(&dir.join(LOCK_FILE))? {
+    let listener = sys::bind(dir)?;
+    return Ok(Instance::Primary(Server {
+      _lock: lock,
+      listener,
+    }));
+  }
+
+  let message = serde_json::to_vec(args).map_err(io::Error::other)?;
+  let deadline = Instant::now() + CONNECT_TIMEOUT;
+  loop {
+    // The primary may hold the lock but not be listening yet.
+    match sys::connect(dir) {
+      Ok(mut stream) => {
+        stream.write_all(&message)?;
+        return Ok(Instance::Secondary);
+      }
+      Err(e) if Instant::now() >= deadline => return Err(e),
+      Err(_) => thread::sleep(Duration::from_millis(50)),
+    }
+  }
+}
+
+/// Exits this process if another instance is already running; otherwise
+/// starts forwarding later launches to the main window.
+pub fn enforce(app: &App) {
+  let Some(dir) = paths::instance_dir(app.handle()) else {
+    log::warn!("no directory for the instance lock, skipping single-instance check");
+    return;
+  };
+  let args: Vec<String> = std::env::args().skip(1).collect();
+
+  match acquire(&dir, &args) {
+    Ok(Instance::Secondary) => {
+      log::info!("Soul Sense is already running, forwarded arguments to it");
+      std::process::exit(0);
+    }
+    Ok(Instance::Primary(server)) => {
+      let app = app.handle().clone();
+      server.listen(move |args| on_forwarded(&app, args));
+    }
+    Err(e) => log::warn!("single-instance check failed, continuing: {e}"),
+  }
+}
+
+fn on_forwarded(app: &AppHandle, args: Vec<String>) {
+  log::info!("another launch forwarded {} argument(s)", args.len());
+  window::focus_main_window(app);
+  let args = deep_link::open_links(app, args);
+  if let Err(e) = app.emit(ARGS_EVENT, ForwardedArgs { args }) {
+    log::warn!("failed to emit {ARGS_EVENT}: {e}");
+  }
+}
+
+#[cfg(unix)]
+mod sys {
+  use std::fs::{self, File, OpenOptions};
+  use std::io;
+  use std::os::unix::io::AsRawFd;
+  use std::os::unix::net::{UnixListener, UnixStream};
+  use std::path::Path;
+
+  pub type Listener = UnixListener;
+
+  const SOCKET_FILE: &str = "instance.sock";
+
+  /// Takes an exclusive `flock`, released by the kernel when the file is
+  /// closed or the process dies.
+  pub fn try_lock(path: &Path) -> io::Result<Option<File>> {
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(path)?;
+    // SAFETY: the descriptor is valid for the lifetime of `file`.
+    let locked = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0;
+    Ok(locked.then_some(file))
+  }
+
+  /// Binds the socket, replacing one left behind by a crashed primary. Only
+  /// the lock holder calls this, so no live socket can be removed.
+  pub fn bind(dir: &Path) -> io::Result<UnixListener> {
+    let path = dir.join(SOCKET_FILE);
+    match fs::remove_file(&path) {
+      Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+      _ => {}
+    }
+    UnixListener::bind(path)
+  }
+
+  pub fn connect(dir: &Path) -> io::Result<UnixStream> {
+    UnixStream::connect(dir.join(SOCKET_FILE))
+  }
+}
+
+#[cfg(not(unix))]
+mod sys {
+  use std::fs::{self, File, OpenOptions};
+  use std::io;
+  use std::net::{Ipv4Addr, TcpListener, TcpStream};
+  use std::os::windows::fs::OpenOptionsExt;
+  use std::path::Path;
+
+  pub type Listener = TcpListener;
+
+  const PORT_FILE: &str = "instance.port";
+  const ERROR_SHARING_VIOLATION: i32 = 32;
+
+  /// Opens the lock file without sharing, which fails while the primary
+  /// instance keeps it open.
+  pub fn try_lock(path: &Path) -> io::Result<Option<File>> {
+    match OpenOptions::new().create(true).truncate(false).write(true).share_mode(0).open(path) {
+      Ok(file) => Ok(Some(file)),
+      Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => Ok(None),
+      Err(e) => Err(e),
+    }
+  }
+
+  pub fn bind(dir: &Path) -> io::Result<TcpListener> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    fs::write(dir.join(PORT_FILE), listener.local_addr()?.port().to_string())?;
+    Ok(listener)
+  }
+
+  pub fn connect(dir: &Path) -> io::Result<TcpStream> {
+    let port: u16 = fs::read_to_string(dir.join(PORT_FILE))?
+      .trim()
+      .parse()
+      .map_err(io::Error::other)?;
+    TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+  use std::sync::mpsc;
+
+  use super::*;
+
+  /// A fresh directory per test, so tests can run in parallel.
+  fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("soulsense-instance-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+  }
+
+  #[test]
+  fn second_launch_forwards_its_args_to_the_primary() {
+    let dir = test_dir("forward");
+    let Instance::Primary(server) = acquire(&dir, &[]).unwrap() else {
+      panic!("first launch should become the primary instance");
+    };
+    let (tx, rx) = mpsc::channel();
+    server.listen(move |args| tx.send(args).unwrap());
+
+    let args = vec!["soulsense://journal/new".to_string()];
+    assert!(matches!(acquire(&dir, &args).unwrap(), Instance::Secondary));
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), args);
+  }
+
+  #[test]
+  fn lock_is_released_with_the_primary() {
+    let dir = test_dir("release");
+    let first = acquire(&dir, &[]).unwrap();
+    assert!(matches!(first, Instance::Primary(_)));
+    drop(first);
+
+    assert!(matches!(acquire(&dir, &[]).unwrap(), Instance::Primary(_)));
+  }
+
+  #[test]
+  fn stale_endpoint_from_a_crashed_primary_is_replaced() {
+    let dir = test_dir("stale");
+    drop(acquire(&dir, &[]).unwrap());
+
+    let Instance::Primary(server) = acquire(&dir, &[]).unwrap() else {
+      panic!("stale endpoint should not block a new primary");
+    };
+    let (tx, rx) = mpsc::channel();
+    server.listen(move |args| tx.send(args).unwrap());
+
+    assert!(matches!(acquire(&dir, &["--hidden".into()]).unwrap(), Instance::Secondary));
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), ["--hidden"]);
+  }
+
+  #[test]
+  fn silent_client_does_not_block_later_launches() {
+    let dir = test_dir("silent");
+    let Instance::Primary(server) = acquire(&dir, &[]).unwrap() else {
+      panic!("first launch should become the primary instance");
+    };
+    let (tx, rx) = mpsc::channel();
+    server.listen(move |args| tx.send(args).unwrap());
+
+    // Connects but never writes or closes
+    let _silent = sys::connect(&dir).unwrap();
+
+    let args = vec!["--hidden".to_string()];
+    assert!(matches!(acquire(&dir, &args).unwrap(), Instance::Secondary));
+    assert_eq!(rx.recv_timeout(READ_TIMEOUT * 3).unwrap(), args);
+  }
+}
@@ -0,0 +1,273 @@
+//! Storage for the refresh token.
+//!
+//! The token is kept in `session.bin` in the app data dir, encrypted with
+//! ChaCha20-Poly1305 under a key derived from a random secret generated on
+//! first use. The secret lives in `install.key` in the app local data dir, so
+//! the token stays unreadable when the data dir is roamed, backed up or
+//! copied without it. It does not stop other programs running as the user;
+//! builds with the `os-keyring` feature keep the token in the OS credential
+//! store instead, and only fall back to the file if the store is unavailable.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::paths::write_private;
+
+const TOKEN_FILE: &str = "session.bin";
+const SECRET_FILE: &str = "install.key";
+const FORMAT_VERSION: u8 = 1;
+const SECRET_BYTES: usize = 32;
+const NONCE_BYTES: usize = 12;
+/// Binds the key and ciphertext to their purpose.
+const CONTEXT: &[u8] = b"soulsense refresh token v1";
+
+#[cfg(feature = "os-keyring")]
+const KEYRING_USER: &str = "refresh-token";
+
+pub struct TokenStore {
+  file: EncryptedFile,
+  #[cfg(feature = "os-keyring")]
+  keyring: Option<keyring::Entry>,
+}
+
+impl TokenStore {
+  pub fn new(app: &AppHandle) -> tauri::Result<Self> {
+    let file = EncryptedFile {
+      path: app.path().app_data_dir()?.join(TOKEN_FILE),
+      secret_path: app.path().app_local_data_dir()?.join(SECRET_FILE),
+    };
+
+    #[cfg(feature = "os-keyring")]
+    let keyring = match keyring::Entry::new(&app.config().identifier, KEYRING_USER) {
+      Ok(entry) => Some(entry),
+      Err(e) => {
+        log::warn!("OS keyring unavailable, using an encrypted file: {e}");
+        None
+      }
+    };
+
+    Ok(Self {
+      file,
+      #[cfg(feature = "os-keyring")]
+      keyring,
+    })
+  }
+
+  pub fn load(&self) -> Result<Option<String>, String> {
+    #[cfg(feature = "os-keyring")]
+    if let Some(entry) = &self.keyring {
+      match entry.get_password() {
+        Ok(token) => return Ok(Some(token)),
+        // It may still be in the file from before the keyring was available
+        Err(keyring::Error::NoEntry) => {}
+        Err(e) => log::warn!("failed to read the OS keyring: {e}"),
+      }
+    }
+    self.file.load()
+  }
+
+  pub fn save(&self, token: &str) -> Result<(), String> {
+    #[cfg(feature = "os-keyring")]
+    if let Some(entry) = &self.keyring {
+      match entry.set_password(token) {
+        // Don't leave an older token behind in the file
+        Ok(()) => return self.file.clear(),
+        Err(e) => log::warn!("failed to write the OS keyring, using an encrypted file: {e}"),
+      }
+    }
+    self.file.save(token)
+  }
+
+  pub fn clear(&self) -> Result<(), String> {
+    #[cfg(feature = "os-keyring")]
+    if let Some(entry) = &self.keyring {
+      match entry.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {}
+        Err(e) => log::warn!("failed to clear the OS keyring: {e}"),
+      }
+    }
+    self.file.clear()
+  }
+}
+
+struct EncryptedFile {
+  path: PathBuf,
+  secret_path: PathBuf,
+}
+
+impl EncryptedFile {
+  fn load(&self) -> Result<Option<String>, String> {
+    let contents = match fs::read(&self.path) {
+      Ok(contents) => contents,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+      Err(e) => return Err(format!("failed to read {}: {e}", self.path.display())),
+    };
+    match decrypt(&self.key()?, &contents) {
+      Some(token) => Ok(Some(token)),
+      None => {
+        // Corrupted, or written under a secret that has since been lost
+        log::warn!("discarding unreadable {}", self.path.display());
+        self.clear()?;
+        Ok(None)
+      }
+    }
+  }
+
+  fn save(&self, token: &str) -> Result<(), String> {
+    let contents = encrypt(&self.key()?, token)?;
+    write_private(&self.path, &contents)
+      .map_err(|e| format!("failed to write {}: {e}", self.path.display()))
+  }
+
+  fn clear(&self) -> Result<(), String> {
+    match fs::remove_file(&self.path) {
+      Ok(()) => Ok(()),
+      Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+      Err(e) => Err(format!("failed to remove {}: {e}", self.path.display())),
+    }
+  }
+
+  /// Derives the key from the install secret, generating the secret if it
+  /// is missing or invalid.
+  fn key(&self) -> Result<Key, String> {
+    let secret = match fs::read(&self.secret_path) {
+      Ok(secret) if secret.len() == SECRET_BYTES => secret,
+      _ => {
+        let mut secret = vec![0u8; SECRET_BYTES];
+        getrandom::fill(&mut secret).map_err(|e| e.to_string())?;
+        write_private(&self.secret_path, &secret)
+          .map_err(|e| format!("failed to write {}: {e}", self.secret_path.display()))?;
+        secret
+      }
+    };
+    let digest = Sha256::new()
+      .chain_update(CONTEXT)
+      .chain_update(&secret)
+      .finalize();
+    Ok(Key::clone_from_slice(&digest))
+  }
+}
+
+/// Encrypts `token` as a version byte, a random nonce and the ciphertext.
+fn encrypt(key: &Key, token: &str) -> Result<Vec<u8>, String> {
+  let mut nonce = [0u8; NONCE_BYTES];
+  getrandom::fill(&mut nonce).map_err(|e| e.to_string())?;
+  let payload = Payload {
+    msg: token.as_bytes(),
+    aad: CONTEXT,
+  };
+  let ciphertext = ChaCha20Poly1305::new(key)
+    .encrypt(Nonce::from_slice(&nonce), payload)
+    .map_err(|_| "failed to encrypt the refresh token".to_string())?;
+
+  let mut contents = vec![FORMAT_VERSION];
+  contents.extend_from_slice(&nonce);
+  contents.extend_from_slice(&ciphertext);
+  Ok(contents)
+}
+
+fn decrypt(key: &Key, contents: &[u8]) -> Option<String> {
+  let (&version, rest) = contents.split_first()?;
+  if version != FORMAT_VERSION || rest.len() < NONCE_BYTES {
+    return None;
+  }
+  let (nonce, ciphertext) = rest.split_at(NONCE_BYTES);
+  let payload = Payload {
+    msg: ciphertext,
+    aad: CONTEXT,
+  };
+  let token = ChaCha20Poly1305::new(key)
+    .decrypt(Nonce::from_slice(nonce), payload)
+    .ok()?;
+  String::from_utf8(token).ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A fresh directory per test, so tests can run in parallel.
+  fn test_file(name: &str) -> EncryptedFile {
+    let dir = std::env::temp_dir().join(format!("soulsense-token-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    EncryptedFile {
+      path: dir.join(TOKEN_FILE),
+      secret_path: dir.join(SECRET_FILE),
+    }
+  }
+
+  fn key(byte: u8) -> Key {
+    Key::clone_from_slice(&[byte; 32])
+  }
+
+  #[test]
+  fn encrypted_token_round_trips() {
+    let contents = encrypt(&key(1), "refresh-token").unwrap();
+
+    assert_eq!(contents[0], FORMAT_VERSION);
+    assert_eq!(decrypt(&key(1), &contents).as_deref(), Some("refresh-token"));
+  }
+
+  #[test]
+  fn wrong_key_is_rejected() {
+    let contents = encrypt(&key(1), "refresh-token").unwrap();
+
+    assert_eq!(decrypt(&key(2), &contents), None);
+  }
+
+  #[test]
+  fn unknown_version_is_rejected() {
+    let mut contents = encrypt(&key(1), "refresh-token").unwrap();
+    contents[0] = FORMAT_VERSION + 1;
+
+    assert_eq!(decrypt(&key(1), &contents), None);
+  }
+
+  #[test]
+  fn tampered_ciphertext_is_rejected() {
+    let mut contents = encrypt(&key(1), "refresh-token").unwrap();
+    *contents.last_mut().unwrap() ^= 1;
+
+    assert_eq!(decrypt(&key(1), &contents), None);
+    assert_eq!(decrypt(&key(1), &[]), None);
+  }
+
+  #[test]
+  fn file_round_trips_under_the_install_secret() {
+    let file = test_file("round-trip");
+    assert_eq!(file.load().unwrap(), None);
+
+    file.save("refresh-token").unwrap();
+    assert_eq!(file.load().unwrap().as_deref(), Some("refresh-token"));
+
+    file.clear().unwrap();
+    assert_eq!(file.load().unwrap(), None);
+  }
+
+  #[test]
+  fn truncated_file_is_discarded() {
+    let file = test_file("truncated");
+    file.save("refresh-token").unwrap();
+    let contents = fs::read(&file.path).unwrap();
+    fs::write(&file.path, &contents[..NONCE_BYTES]).unwrap();
+
+    assert_eq!(file.load().unwrap(), None);
+    assert!(!file.path.exists());
+  }
+
+  #[test]
+  fn file_from_a_lost_secret_is_discarded() {
+    let file = test_file("lost-secret");
+    file.save("refresh-token").unwrap();
+    fs::remove_file(&file.secret_path).unwrap();
+
+    assert_eq!(file.load().unwrap(), None);
+    assert!(!file.path.exists());
+  }
+}
@@ -0,0 +1,184 @@
+//! System tray icon with quick actions and background mode.
+//!
+//! With `closeToTray` enabled, closing the main window hides it instead of
+//! quitting, so the sidecar and reminders keep running until "Quit" is picked
+//! from the tray.
+
+use tauri::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{App, AppHandle, Manager, WindowEvent};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+
+use crate::readiness;
+use crate::settings::SettingsStore;
+use crate::sidecar::{SidecarState, SidecarStatus};
+use crate::window::{self, EXAM_START_ROUTE, MAIN_WINDOW};
+
+const TRAY_ID: &str = "main";
+
+const OPEN: &str = "open";
+const QUICK_JOURNAL: &str = "quick-journal";
+const CHECK_IN: &str = "check-in";
+const BACKEND_STATUS: &str = "backend-status";
+const CLOSE_TO_TRAY: &str = "close-to-tray";
+const QUIT: &str = "quit";
+
+/// Menu items whose state the shell updates after creation.
+pub struct TrayState {
+  close_to_tray: CheckMenuItem<tauri::Wry>,
+}
+
+/// Creates the tray icon and hooks the main window's close button.
+pub fn create(app: &App) -> tauri::Result<()> {
+  let close_to_tray = app.state::<SettingsStore>().get().close_to_tray;
+  let close_to_tray_item = CheckMenuItem::with_id(
+    app,
+    CLOSE_TO_TRAY,
+    "Keep running in tray",
+    true,
+    close_to_tray,
+    None::<&str>,
+  )?;
+  let menu = Menu::with_items(
+    app,
+    &[
+      &MenuItem::with_id(app, OPEN, "Open Soul Sense", true, None::<&str>)?,
+      &MenuItem::with_id(
+        app,
+        QUICK_JOURNAL,
+        "Quick journal entry",
+        true,
+        None::<&str>,
+      )?,
+      &MenuItem::with_id(app, CHECK_IN, "Start check-in exam", true, None::<&str>)?,
+      &PredefinedMenuItem::separator(app)?,
+      &MenuItem::with_id(app, BACKEND_STATUS, "Backend status", true, None::<&str>)?,
+      &close_to_tray_item,
+      &PredefinedMenuItem::separator(app)?,
+      &MenuItem::with_id(app, QUIT, "Quit", true, None::<&str>)?,
+    ],
+  )?;
+
+  let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+    .tooltip("Soul Sense")
+    .menu(&menu)
+    .show_menu_on_left_click(false)
+    .on_menu_event(on_menu_event)
+    .on_tray_icon_event(on_tray_icon_event);
+  if let Some(icon) = app.default_window_icon() {
+    builder = builder.icon(icon.clone());
+  }
+  builder.build(app)?;
+
+  app.manage(TrayState {
+    close_to_tray: close_to_tray_item,
+  });
+
+  if let Some(main) = app.get_webview_window(MAIN_WINDOW) {
+    let handle = app.handle().clone();
+    main.on_window_event(move |event| {
+      if let WindowEvent::CloseRequested { api, .. } = event {
+        if handle.state::<SettingsStore>().get().close_to_tray {
+          api.prevent_close();
+          if let Some(main) = handle.get_webview_window(MAIN_WINDOW) {
+            let _ = main.hide();
+          }
+        }
+      }
+    });
+  }
+  Ok(())
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+  match event.id().as_ref() {
+    OPEN => window::focus_main_window(app),
+    QUICK_JOURNAL => {
+      if let Err(e) = window::open_quick_journal(app) {
+        log::warn!("failed to open quick journal: {e}");
+      }
+    }
+    CHECK_IN => window::show_route(app, EXAM_START_ROUTE),
+    BACKEND_STATUS => show_backend_status(app),
+    CLOSE_TO_TRAY => {
+      let enabled = app
+        .state::<TrayState>()
+        .close_to_tray
+        .is_checked()
+        .unwrap_or(false);
+      if let Err(e) = persist_close_to_tray(app, enabled) {
+        log::warn!("failed to save tray preference: {e}");
+      }
+    }
+    QUIT => app.exit(0),
+    _ => {}
+  }
+}
+
+fn on_tray_icon_event(tray: &TrayIcon, event: TrayIconEvent) {
+  if let TrayIconEvent::Click {
+    button: MouseButton::Left,
+    button_state: MouseButtonState::Up,
+    ..
+  } = event
+  {
+    window::focus_main_window(tray.app_handle());
+  }
+}
+
+/// Shows the sidecar status in a native dialog, offering a restart when the
+/// supervisor has given up.
+fn show_backend_status(app: &AppHandle) {
+  let status = app.state::<SidecarState>().status();
+  let message = match &status {
+    SidecarStatus::Starting { attempt } => format!("The backend is starting (attempt {attempt})."),
+    SidecarStatus::Running { pid } => format!("The backend is running (pid {pid})."),
+    SidecarStatus::Reconnecting { delay_ms, .. } => {
+      format!("The backend stopped unexpectedly and restarts in {delay_ms} ms.")
+    }
+    SidecarStatus::Failed { reason } => format!("The backend is not running.\n\n{reason}"),
+    SidecarStatus::Stopped => "The backend is stopped.".to_string(),
+  };
+
+  let dialog = app.dialog().message(message).title("Backend status");
+  if matches!(status, SidecarStatus::Failed { .. }) {
+    let app = app.clone();
+    dialog
+      .buttons(MessageDialogButtons::OkCancelCustom(
+        "Restart".to_string(),
+        "Close".to_string(),
+      ))
+      .show(move |restart| {
+        if restart {
+          readiness::readiness_retry(app);
+        }
+      });
+  } else {
+    dialog.show(|_| {});
+  }
+}
+
+fn persist_close_to_tray(app: &AppHandle, enabled: bool) -> Result<(), String> {
+  app
+    .state::<SettingsStore>()
+    .update(|settings| settings.close_to_tray = enabled)?;
+  log::info!(
+    "close to tray {}",
+    if enabled { "enabled" } else { "disabled" }
+  );
+  Ok(())
+}
+
+/// Returns whether closing the main window hides it to the tray.
+#[tauri::command]
+pub fn close_to_tray(settings: tauri::State<'_, SettingsStore>) -> bool {
+  settings.get().close_to_tray
+}
+
+/// Enables or disables hiding to the tray on close, and persists the choice.
+#[tauri::command]
+pub fn set_close_to_tray(app: AppHandle, enabled: bool) -> Result<(), String> {
+  persist_close_to_tray(&app, enabled)?;
+  let _ = app.state::<TrayState>().close_to_tray.set_checked(enabled);
+  Ok(())
+}
@@ -0,0 +1,126 @@
+//! Creation of the application windows.
+//!
+//! Windows are declared in `tauri.conf.json` with `"create": false` and built
+//! here, so the shell can attach runtime configuration such as the API URL
+//! before the frontend loads.
+
+use std::path::PathBuf;
+
+use tauri::utils::config::WindowConfig;
+use tauri::{App, AppHandle, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+use crate::app_lock;
+use crate::proxy;
+use crate::readiness::{ReadinessState, SPLASH_WINDOW};
+use crate::window_state;
+
+pub const MAIN_WINDOW: &str = "main";
+pub const QUICK_JOURNAL_WINDOW: &str = "quick-journal";
+
+/// Frontend routes the shell opens on its own.
+pub const JOURNAL_NEW_ROUTE: &str = "/journal/new";
+pub const EXAM_START_ROUTE: &str = "/exam/start";
+
+/// Builds the main window from its `tauri.conf.json` entry.
+///
+/// The window reopens where it was last closed and starts hidden; it is
+/// revealed once the backend is ready.
+pub fn create_main_window(app: &App) -> tauri::Result<WebviewWindow> {
+  let mut config = app
+    .config()
+    .app
+    .windows
+    .iter()
+    .find(|w| w.label == MAIN_WINDOW)
+    .cloned()
+    .unwrap_or_default();
+  window_state::restore(app.handle(), &mut config);
+
+  let window = WebviewWindowBuilder::from_config(app, &config)?
+    .initialization_script(proxy::initialization_script())
+    .initialization_script(app_lock::activity_script())
+    .visible(false)
+    .build()?;
+  window_state::track(&window);
+  app_lock::track(&window);
+  Ok(window)
+}
+
+/// Brings the app to the front: the splash while the backend is starting,
+/// the lock window while locked, and the main window otherwise.
+pub fn focus_main_window(app: &AppHandle) {
+  if let Some(splash) = app.get_webview_window(SPLASH_WINDOW) {
+    let _ = splash.set_focus();
+    return;
+  }
+  if !app_lock::may_show(app, MAIN_WINDOW) {
+    return;
+  }
+  if let Some(main) = app.get_webview_window(MAIN_WINDOW) {
+    let _ = main.unminimize();
+    let _ = main.show();
+    let _ = main.set_focus();
+  }
+}
+
+/// Shows `route` in the main window and brings the app to the front.
+///
+/// `route` may carry a query string. While the backend is starting the main
+/// window is navigated in the background and revealed on `/ready` as usual.
+pub fn show_route(app: &AppHandle, route: &str) {
+  let (path, query) = match route.split_once('?') {
+    Some((path, query)) => (path, Some(query)),
+    None => (route, None),
+  };
+  if let Some(main) = app.get_webview_window(MAIN_WINDOW) {
+    match main.url() {
+      Ok(mut url) => {
+        url.set_path(path);
+        url.set_query(query);
+        if let Err(e) = main.navigate(url) {
+          log::warn!("failed to open {path}: {e}");
+        }
+      }
+      Err(e) => log::warn!("failed to open {path}: {e}"),
+    }
+  }
+  focus_main_window(app);
+}
+
+/// Opens the small quick journal window, or focuses it if it is already open.
+///
+/// The window needs the backend, so until it is ready the splash is focused
+/// instead.
+pub fn open_quick_journal(app: &AppHandle) -> tauri::Result<()> {
+  if !app_lock::may_show(app, QUICK_JOURNAL_WINDOW) {
+    return Ok(());
+  }
+  if let Some(window) = app.get_webview_window(QUICK_JOURNAL_WINDOW) {
+    let _ = window.unminimize();
+    return window.set_focus();
+  }
+  if !app.state::<ReadinessState>().is_ready() {
+    focus_main_window(app);
+    return Ok(());
+  }
+
+  let route = JOURNAL_NEW_ROUTE.trim_start_matches('/');
+  let mut config = WindowConfig {
+    label: QUICK_JOURNAL_WINDOW.to_string(),
+    url: WebviewUrl::App(PathBuf::from(route)),
+    title: "Quick journal entry".to_string(),
+    width: 480.0,
+    height: 420.0,
+    center: true,
+    ..Default::default()
+  };
+  window_state::restore(app, &mut config);
+
+  let window = WebviewWindowBuilder::from_config(app, &config)?
+    .initialization_script(proxy::initialization_script())
+    .initialization_script(app_lock::activity_script())
+    .build()?;
+  window_state::track(&window);
+  app_lock::track(&window);
+  window.set_focus()
+}
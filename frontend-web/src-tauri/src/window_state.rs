@@ -0,0 +1,374 @@
+//! Window geometry persisted as `window-state.json` in the app config dir.
+//!
+//! Each tracked window records its last normal (non-maximized) bounds in
+//! logical pixels, whether it was maximized or fullscreen, and the route it
+//! was showing. The state is written when the window closes and when the app
+//! exits, and applied to the window config before the window is built, so it
+//! opens in place instead of jumping after the first paint.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::utils::config::WindowConfig;
+use tauri::{AppHandle, Manager, Monitor, WebviewUrl, WebviewWindow, WindowEvent};
+
+use crate::paths;
+use crate::util::lock;
+
+const FILE_NAME: &str = "window-state.json";
+
+/// Smallest size a restored window is allowed to shrink to.
+const MIN_WIDTH: f64 = 320.0;
+const MIN_HEIGHT: f64 = 240.0;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WindowState {
+  pub x: Option<f64>,
+  pub y: Option<f64>,
+  pub width: Option<f64>,
+  pub height: Option<f64>,
+  pub maximized: bool,
+  pub fullscreen: bool,
+  /// Path of the last page shown, without its query string so one-off
+  /// tokens never end up on disk.
+  pub route: Option<String>,
+}
+
+pub struct WindowStateStore {
+  path: PathBuf,
+  windows: Mutex<HashMap<String, WindowState>>,
+}
+
+impl WindowStateStore {
+  /// Loads the state file, starting empty if it is missing or unreadable.
+  pub fn load(app: &AppHandle) -> tauri::Result<Self> {
+    let path = app.path().app_config_dir()?.join(FILE_NAME);
+    let windows = match fs::read_to_string(&path) {
+      Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+        log::warn!("ignoring invalid {}: {e}", path.display());
+        HashMap::new()
+      }),
+      Err(_) => HashMap::new(),
+    };
+    Ok(Self {
+      path,
+      windows: Mutex::new(windows),
+    })
+  }
+
+  fn get(&self, label: &str) -> Option<WindowState> {
+    lock(&self.windows).get(label).cloned()
+  }
+
+  fn update(&self, label: &str, change: impl FnOnce(&mut WindowState)) {
+    let mut windows = lock(&self.windows);
+    change(windows.entry(label.to_string()).or_default());
+  }
+
+  /// Writes the state of every window seen so far back to disk.
+  pub fn save(&self) -> Result<(), String> {
+    let windows = lock(&self.windows);
+    let contents = serde_json::to_string_pretty(&*windows).map_err(|e| e.to_string())?;
+    paths::write_private(&self.path, contents.as_bytes()).map_err(|e| e.to_string())
+  }
+}
+
+/// Applies the saved state for `config.label`, clamped to the monitors that
+/// are currently connected.
+pub fn restore(app: &AppHandle, config: &mut WindowConfig) {
+  let Some(state) = app.state::<WindowStateStore>().get(&config.label) else {
+    return;
+  };
+
+  if let (Some(width), Some(height)) = (state.width, state.height) {
+    config.width = width;
+    config.height = height;
+  }
+  if let (Some(x), Some(y)) = (state.x, state.y) {
+    config.x = Some(x);
+    config.y = Some(y);
+    config.center = false;
+  }
+  let monitors = app.available_monitors().unwrap_or_default();
+  clamp_to_monitors(config, &monitors);
+
+  config.maximized = state.maximized;
+  config.fullscreen = state.fullscreen;
+  if let Some(route) = state.route {
+    config.url = WebviewUrl::App(PathBuf::from(route.trim_start_matches('/')));
+  }
+}
+
+/// Keeps `window`'s entry up to date and persists it when the window closes.
+pub fn track(window: &WebviewWindow) {
+  let tracked = window.clone();
+  window.on_window_event(move |event| match event {
+    WindowEvent::Moved(_) | WindowEvent::Resized(_) => record_bounds(&tracked),
+    WindowEvent::CloseRequested { .. } => {
+      capture(&tracked);
+      if let Err(e) = tracked.state::<WindowStateStore>().save() {
+        log::warn!("failed to save window state: {e}");
+      }
+    }
+    _ => {}
+  });
+}
+
+/// Captures every open window that has an entry and writes the file; called
+/// on exit for windows that never received a close request.
+pub fn save_all(app: &AppHandle) {
+  let Some(store) = app.try_state::<WindowStateStore>() else {
+    return;
+  };
+  for window in app.webview_windows().values() {
+    if store.get(window.label()).is_some() {
+      capture(window);
+    }
+  }
+  if let Err(e) = store.save() {
+    log::warn!("failed to save window state: {e}");
+  }
+}
+
+/// Records the window's bounds, unless it is maximized, minimized or
+/// fullscreen, in which case the last normal bounds are kept for restoring.
+fn record_bounds(window: &WebviewWindow) {
+  let unusual = window.is_maximized().unwrap_or(false)
+    || window.is_minimized().unwrap_or(false)
+    || window.is_fullscreen().unwrap_or(false);
+  if unusual {
+    return;
+  }
+  let (Ok(scale), Ok(position), Ok(size)) = (
+    window.scale_factor(),
+    window.outer_position(),
+    window.inner_size(),
+  ) else {
+    return;
+  };
+  let position = position.to_logical::<f64>(scale);
+  let size = size.to_logical::<f64>(scale);
+  window.state::<WindowStateStore>().update(window.label(), |state| {
+    state.x = Some(position.x);
+    state.y = Some(position.y);
+    state.width = Some(size.width);
+    state.height = Some(size.height);
+  });
+}
+
+fn capture(window: &WebviewWindow) {
+  record_bounds(window);
+  let maximized = window.is_maximized().unwrap_or(false);
+  let fullscreen = window.is_fullscreen().unwrap_or(false);
+  let route = window.url().ok().map(|url| url.path().to_string());
+  window.state::<WindowStateStore>().update(window.label(), |state| {
+    state.maximized = maximized;
+    state.fullscreen = fullscreen;
+    if route.is_some() {
+      state.route = route;
+    }
+  });
+}
+
+/// Rectangle in logical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rect {
+  x: f64,
+  y: f64,
+  width: f64,
+  height: f64,
+}
+
+impl Rect {
+  fn overlap(&self, other: &Rect) -> f64 {
+    let width = (self.x + self.width).min(other.x + other.width) - self.x.max(other.x);
+    let height = (self.y + self.height).min(other.y + other.height) - self.y.max(other.y);
+    width.max(0.0) * height.max(0.0)
+  }
+}
+
+/// Where a restored window opens: its size, and its position, or `None` to
+/// center it.
+#[derive(Debug, PartialEq)]
+struct Placement {
+  width: f64,
+  height: f64,
+  position: Option<(f64, f64)>,
+}
+
+/// Logical work area of a monitor.
+fn work_area(monitor: &Monitor) -> Rect {
+  let scale = monitor.scale_factor();
+  let area = monitor.work_area();
+  let position = area.position.to_logical::<f64>(scale);
+  let size = area.size.to_logical::<f64>(scale);
+  Rect {
+    x: position.x,
+    y: position.y,
+    width: size.width,
+    height: size.height,
+  }
+}
+
+fn clamp_to_monitors(config: &mut WindowConfig, monitors: &[Monitor]) {
+  let areas: Vec<_> = monitors.iter().map(work_area).collect();
+  let position = config.x.zip(config.y);
+  let Some(placement) = place(config.width, config.height, position, &areas) else {
+    return;
+  };
+
+  config.width = placement.width;
+  config.height = placement.height;
+  match placement.position {
+    Some((x, y)) => {
+      config.x = Some(x);
+      config.y = Some(y);
+    }
+    None => {
+      config.x = None;
+      config.y = None;
+      config.center = true;
+    }
+  }
+}
+
+/// Fits the window on the work area it overlaps most, shrinking it if
+/// needed. A window that no longer overlaps any work area, e.g. because it
+/// was last on a display that has since been unplugged, is centered on the
+/// first one. Returns `None` when no monitor is known.
+fn place(
+  width: f64,
+  height: f64,
+  position: Option<(f64, f64)>,
+  areas: &[Rect],
+) -> Option<Placement> {
+  let first = *areas.first()?;
+
+  let target = position.and_then(|(x, y)| {
+    let window = Rect {
+      x,
+      y,
+      width,
+      height,
+    };
+    areas
+      .iter()
+      .map(|area| (area.overlap(&window), *area))
+      .filter(|(overlap, _)| *overlap > 0.0)
+      .max_by(|a, b| a.0.total_cmp(&b.0))
+      .map(|(_, area)| area)
+  });
+  let area = target.unwrap_or(first);
+
+  let width = width.min(area.width).max(MIN_WIDTH);
+  let height = height.min(area.height).max(MIN_HEIGHT);
+  let position = target.and(position).map(|(x, y)| {
+    (
+      x.min(area.x + area.width - width).max(area.x),
+      y.min(area.y + area.height - height).max(area.y),
+    )
+  });
+
+  Some(Placement {
+    width,
+    height,
+    position,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const PRIMARY: Rect = Rect {
+    x: 0.0,
+    y: 0.0,
+    width: 1920.0,
+    height: 1040.0,
+  };
+  const SECONDARY: Rect = Rect {
+    x: 1920.0,
+    y: 0.0,
+    width: 1280.0,
+    height: 1024.0,
+  };
+
+  #[test]
+  fn keeps_a_window_that_fits() {
+    let placement = place(800.0, 600.0, Some((100.0, 50.0)), &[PRIMARY]).unwrap();
+
+    assert_eq!(
+      placement,
+      Placement {
+        width: 800.0,
+        height: 600.0,
+        position: Some((100.0, 50.0)),
+      }
+    );
+  }
+
+  #[test]
+  fn centers_an_off_screen_window() {
+    let placement = place(800.0, 600.0, Some((-5000.0, 4000.0)), &[PRIMARY]).unwrap();
+
+    assert_eq!(placement.position, None);
+    assert_eq!((placement.width, placement.height), (800.0, 600.0));
+  }
+
+  #[test]
+  fn centers_on_the_first_monitor_when_its_monitor_was_removed() {
+    let saved = Some((2000.0, 100.0));
+    assert!(place(1200.0, 900.0, saved, &[PRIMARY, SECONDARY])
+      .unwrap()
+      .position
+      .is_some());
+
+    let placement = place(1200.0, 900.0, saved, &[PRIMARY]).unwrap();
+
+    assert_eq!(placement.position, None);
+  }
+
+  #[test]
+  fn moves_a_partially_visible_window_onto_the_monitor_it_overlaps_most() {
+    // Hanging off the bottom-right corner of the secondary
+    let placement = place(800.0, 600.0, Some((2800.0, 700.0)), &[PRIMARY, SECONDARY]).unwrap();
+
+    assert_eq!(placement.position, Some((2400.0, 424.0)));
+  }
+
+  #[test]
+  fn shrinks_a_window_larger_than_the_work_area() {
+    let placement = place(2500.0, 1400.0, Some((-10.0, -10.0)), &[PRIMARY]).unwrap();
+
+    assert_eq!(
+      placement,
+      Placement {
+        width: 1920.0,
+        height: 1040.0,
+        position: Some((0.0, 0.0)),
+      }
+    );
+  }
+
+  #[test]
+  fn never_shrinks_below_the_minimum_size() {
+    let tiny = Rect {
+      width: 200.0,
+      height: 100.0,
+      ..PRIMARY
+    };
+
+    let placement = place(800.0, 600.0, None, &[tiny]).unwrap();
+
+    assert_eq!((placement.width, placement.height), (MIN_WIDTH, MIN_HEIGHT));
+  }
+
+  #[test]
+  fn leaves_the_config_alone_without_monitors() {
+    assert_eq!(place(800.0, 600.0, Some((0.0, 0.0)), &[]), None);
+  }
+}
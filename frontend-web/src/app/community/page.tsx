@@ -15,6 +15,7 @@ import {
   ProjectRoadmap,
 } from '@/components/dashboard';
 import { MOCK_DASHBOARD_DATA } from '@/lib/dashboard-mock-data';
+import { getApiBaseUrl } from '@/lib/api/base-url';
 import { MissionControl } from '@/components/mission-control';
 import { Users, Star, GitMerge, GitCommit, ArrowRight, Sparkles } from 'lucide-react';
 import { motion, AnimatePresence } from 'framer-motion';
@@ -45,7 +46,7 @@ export default function CommunityDashboard() {
   useEffect(() => {
     async function fetchData() {
       try {
-        const API_BASE = process.env.NEXT_PUBLIC_API_URL || 'http://localhost:8000/api/v1';
+        const API_BASE = getApiBaseUrl();
         const [
           statsRes,
           contributorsRes,
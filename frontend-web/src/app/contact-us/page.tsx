@@ -8,6 +8,7 @@ import { Form, FormField } from '@/components/forms';
 import { Button, Input } from '@/components/ui';
 import { Footer, Section } from '@/components/layout';
 import { contactSchema } from '@/lib/validation';
+import { getApiBaseUrl } from '@/lib/api/base-url';
 import { z } from 'zod';
 
 type ContactFormData = z.infer<typeof contactSchema>;
@@ -21,7 +22,7 @@ export default function ContactUsPage() {
         setIsLoading(true);
         setError(null);
         try {
-            const response = await fetch('http://localhost:8000/api/v1/contact/submit', {
+            const response = await fetch(`${getApiBaseUrl()}/contact/submit`, {
                 method: 'POST',
                 headers: {
                     'Content-Type': 'application/json',
@@ -1,7 +1,7 @@
 import type { Metadata } from 'next';
 import { Inter } from 'next/font/google';
 import '@/styles/globals.css';
-import { ThemeProvider, NavbarController } from '@/components/layout';
+import { ThemeProvider, NavbarController, ReminderPrompt } from '@/components/layout';
 
 const inter = Inter({ subsets: ['latin'], variable: '--font-sans' });
 
@@ -33,6 +33,7 @@ export default function RootLayout({ children }: { children: React.ReactNode })
           <AuthProvider>
             <NavbarController />
             {children}
+            <ReminderPrompt />
           </AuthProvider>
         </ThemeProvider>
       </body>
@@ -8,6 +8,7 @@ import { Form, FormField } from '@/components/forms';
 import { Button, Input } from '@/components/ui';
 import { AuthLayout, SocialLogin } from '@/components/auth';
 import { loginSchema } from '@/lib/validation';
+import { getApiBaseUrl } from '@/lib/api/base-url';
 import { z } from 'zod';
 import { UseFormReturn } from 'react-hook-form';
 
@@ -37,7 +38,7 @@ export default function LoginPage() {
       formData.append('username', data.identifier);
       formData.append('password', data.password);
 
-      const response = await fetch('http://localhost:8000/api/v1/auth/login', {
+      const response = await fetch(`${getApiBaseUrl()}/auth/login`, {
         method: 'POST',
         headers: {
           'Content-Type': 'application/x-www-form-urlencoded',
@@ -84,7 +85,7 @@ export default function LoginPage() {
     setIsLoggingIn(true);
     setTwoFaError('');
     try {
-      const response = await fetch('http://localhost:8000/api/v1/auth/login/2fa', {
+      const response = await fetch(`${getApiBaseUrl()}/auth/login/2fa`, {
         method: 'POST',
         headers: {
           'Content-Type': 'application/json',
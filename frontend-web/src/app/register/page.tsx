@@ -8,6 +8,7 @@ import { Form, FormField } from '@/components/forms';
 import { Button, Input } from '@/components/ui';
 import { AuthLayout, SocialLogin, PasswordStrengthIndicator } from '@/components/auth';
 import { registrationSchema } from '@/lib/validation';
+import { getApiBaseUrl } from '@/lib/api/base-url';
 import { z } from 'zod';
 import { UseFormReturn } from 'react-hook-form';
 
@@ -20,7 +21,7 @@ export default function RegisterPage() {
   const handleSubmit = async (data: RegisterFormData, methods: UseFormReturn<RegisterFormData>) => {
     setIsLoading(true);
     try {
-      const response = await fetch('http://localhost:8000/api/v1/auth/register', {
+      const response = await fetch(`${getApiBaseUrl()}/auth/register`, {
         method: 'POST',
         headers: {
           'Content-Type': 'application/json',
@@ -4,6 +4,7 @@ import React, { useState, useEffect } from 'react';
 import { motion } from 'framer-motion';
 import { Calendar, CheckCircle2, Circle, Clock, TrendingUp } from 'lucide-react';
 import { cn } from '@/lib/utils';
+import { getApiBaseUrl } from '@/lib/api/base-url';
 
 interface Milestone {
   id: number;
@@ -31,7 +32,7 @@ export function ProjectRoadmap({ data }: { data?: Milestone[] }) {
 
     async function fetchRoadmap() {
       try {
-        const res = await fetch('http://127.0.0.1:8000/api/v1/community/roadmap');
+        const res = await fetch(`${getApiBaseUrl()}/community/roadmap`);
         const fetchedData = await res.json();
         setMilestones(fetchedData);
       } catch (err) {
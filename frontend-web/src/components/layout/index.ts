@@ -4,3 +4,4 @@ export * from './section';
 export * from './theme-provider';
 export * from './floating-navbar';
 export * from './navbar-controller';
+export * from './reminder-prompt';
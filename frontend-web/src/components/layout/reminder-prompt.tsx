@@ -0,0 +1,78 @@
+'use client';
+
+import React, { useEffect, useState } from 'react';
+import { usePathname, useRouter } from 'next/navigation';
+import { invoke, isTauri } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { Bell, X } from 'lucide-react';
+import { Button } from '@/components/ui';
+
+/** Payload of the desktop shell's `reminders://fired` event. */
+interface FiredReminder {
+  id: string;
+  kind: 'journal' | 'exam';
+  route: string;
+}
+
+const FIRED_EVENT = 'reminders://fired';
+
+const TITLES: Record<FiredReminder['kind'], string> = {
+  journal: 'Time to journal',
+  exam: 'Time for a check-in',
+};
+
+/**
+ * Offers to open the page a desktop reminder points to. Notifications carry
+ * no click callback, so the shell emits every reminder it shows and this
+ * prompt lets the user follow it without losing what they are doing.
+ */
+export function ReminderPrompt() {
+  const router = useRouter();
+  const pathname = usePathname();
+  const [reminder, setReminder] = useState<FiredReminder | null>(null);
+
+  useEffect(() => {
+    if (!isTauri()) {
+      return;
+    }
+    const unlisten = listen<FiredReminder>(FIRED_EVENT, (event) => setReminder(event.payload));
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
+  if (!reminder || reminder.route === pathname) {
+    return null;
+  }
+
+  const open = () => {
+    setReminder(null);
+    router.push(reminder.route);
+  };
+
+  const snooze = () => {
+    setReminder(null);
+    invoke('snooze_reminder', { id: reminder.id }).catch((e) =>
+      console.warn('Failed to snooze reminder', e)
+    );
+  };
+
+  return (
+    <div
+      role="status"
+      className="fixed bottom-6 right-6 z-50 flex items-center gap-3 rounded-lg border bg-background p-4 shadow-lg"
+    >
+      <Bell className="h-5 w-5 text-primary" />
+      <span className="text-sm font-medium">{TITLES[reminder.kind]}</span>
+      <Button size="sm" onClick={open}>
+        Open
+      </Button>
+      <Button size="sm" variant="outline" onClick={snooze}>
+        Snooze
+      </Button>
+      <Button size="icon" variant="ghost" aria-label="Dismiss" onClick={() => setReminder(null)}>
+        <X className="h-4 w-4" />
+      </Button>
+    </div>
+  );
+}
@@ -9,6 +9,7 @@ import { DataGrid } from './data-grid';
 import { DataFilters } from './filters';
 import { MissionItem, MissionControlData } from './types';
 import { MOCK_DASHBOARD_DATA } from '@/lib/dashboard-mock-data';
+import { getApiBaseUrl } from '@/lib/api/base-url';
 
 interface MissionControlProps {
   className?: string;
@@ -33,7 +34,7 @@ export const MissionControl: React.FC<MissionControlProps> = ({ className }) =>
     setLoading(true);
     try {
       const response = await fetch(
-        `${process.env.NEXT_PUBLIC_API_URL || 'http://127.0.0.1:8000/api/v1'}/community/mission-control`
+        `${getApiBaseUrl()}/community/mission-control`
       );
       if (response.ok) {
         const jsonData = await response.json();
@@ -1,7 +1,8 @@
 import { z } from 'zod';
 import { PasswordResetComplete } from '../validation/schemas'; // We will add this type to schemas
+import { getApiBaseUrl } from './base-url';
 
-const API_Base = process.env.NEXT_PUBLIC_API_URL || 'http://localhost:8000/api/v1';
+const API_Base = getApiBaseUrl();
 
 export const authApi = {
   async initiatePasswordReset(email: string): Promise<{ message: string }> {
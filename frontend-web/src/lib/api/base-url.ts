@@ -0,0 +1,13 @@
+declare global {
+  interface Window {
+    /** Injected by the desktop shell with the sidecar's resolved API URL. */
+    __SOULSENSE_API_URL__?: string;
+  }
+}
+
+export function getApiBaseUrl(): string {
+  if (typeof window !== 'undefined' && window.__SOULSENSE_API_URL__) {
+    return window.__SOULSENSE_API_URL__;
+  }
+  return process.env.NEXT_PUBLIC_API_URL || 'http://localhost:8000/api/v1';
+}
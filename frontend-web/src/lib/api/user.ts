@@ -1,4 +1,6 @@
-const API_Base = process.env.NEXT_PUBLIC_API_URL || 'http://localhost:8000/api/v1';
+import { getApiBaseUrl } from './base-url';
+
+const API_Base = getApiBaseUrl();
 
 export const userApi = {
   async getAuditLogs(page: number = 1, limit: number = 20) {
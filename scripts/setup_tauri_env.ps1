@@ -40,7 +40,9 @@ try {
     
     # Build the sidecar
     cd .. # Go to project root if script is run from scripts/
-    python -m PyInstaller --onefile --name soul-sense-backend --clean backend/fastapi/sidecar.py
+    python -m pip install -r backend/fastapi/requirements.txt
+    # sqlcipher3 is only imported lazily, for encrypted databases
+    python -m PyInstaller --onefile --name soul-sense-backend --clean --hidden-import sqlcipher3 --hidden-import sqlcipher3.dbapi2 backend/fastapi/sidecar.py
     
     # Identify target triple
     $target = & rustc -vV | Select-String "host: " | ForEach-Object { $_.ToString().Split(": ")[1].Trim() }